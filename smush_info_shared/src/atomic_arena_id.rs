@@ -23,6 +23,7 @@ impl AtomicArenaId {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(val: &str) -> Self {
         let mut bytes = val.bytes();
         
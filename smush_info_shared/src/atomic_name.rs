@@ -26,6 +26,7 @@ impl AtomicName {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(val: &str) -> Self {
         let utf16le: Vec<u16> = val.encode_utf16().collect();
         let mut result = [0; 32];
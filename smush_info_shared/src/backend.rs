@@ -0,0 +1,492 @@
+//! What the plugin's hooks do with what they read from the game, kept apart from the reading so
+//! it can be tested without the game. The plugin implements [`GameBackend`] and [`Fighter`] over
+//! the game's own functions and memory.
+use std::sync::Mutex;
+use std::sync::atomic::Ordering;
+
+use crate::{Info, Character};
+use crate::clock::{self, MatchMode};
+use crate::results::{self, MatchEnd};
+use crate::ruleset;
+use crate::stats;
+use crate::timeline::{self, KoType};
+
+/// `current_menu` on the controls screen. It has a fighter entry (a sandbag and Mario), which
+/// must not count as a match.
+pub const CONTROLS_MENU: u32 = 0x6020000;
+
+// what the plugin has always reported as the remaining time outside of a match (`-1.0 as u32`)
+const NO_REMAINING_FRAMES: u32 = 0;
+
+/// Game-wide state.
+pub trait GameBackend {
+    /// Fighters the fighter manager has loaded, or `None` while there's no fighter manager yet.
+    fn fighter_entry_count(&self) -> Option<u32>;
+    fn is_result_mode(&self) -> bool;
+    fn current_menu(&self) -> u32;
+    /// Holds the full time limit until GO, and something past any time limit in untimed matches.
+    fn remaining_time_as_frame(&self) -> u32;
+    /// Whether the countdown is over and the fighters can move.
+    fn is_ready_go(&self) -> bool;
+    /// Only asked until GO.
+    fn match_mode(&self) -> MatchMode;
+    /// The name tag the player on `port` picked, as shown in game.
+    fn player_tag(&self, port: usize) -> String;
+    /// Where the fighter on `port` placed, 1 for first. Only asked about fighters in the match,
+    /// once the game is in result mode.
+    fn placement(&self, port: usize) -> Option<u32>;
+    /// Whether teammates can hurt each other in the current match.
+    fn friendly_fire(&self) -> bool;
+}
+
+/// One fighter, as seen from one of its status hooks.
+pub trait Fighter {
+    /// Also the fighter's port.
+    fn entry_id(&self) -> usize;
+    fn character(&self) -> Character;
+    fn damage(&self) -> f32;
+    fn stock_count(&self) -> u32;
+    fn self_destructs(&self) -> u32;
+    fn is_cpu(&self) -> bool;
+    /// Costume slot, 0-indexed.
+    fn skin(&self) -> u32;
+    /// Entry id of whoever hit this fighter last, if anyone has.
+    fn last_attacker(&self) -> Option<usize>;
+    /// How the fighter is being KO'd. Only asked once it's dying.
+    fn ko_type(&self) -> KoType;
+    /// How far the fighter is out of the stage's plane, negative into the background.
+    fn depth(&self) -> f32;
+    /// The fighter's team, in team battles.
+    fn team(&self) -> Option<u32>;
+}
+
+/// Polled from the plugin's update thread: whether a match is on, the clock and the menu, and the
+/// result once the match is over. Also resets the stats and stock losses when a match starts, and
+/// reads the ruleset until GO.
+pub fn update_match_state(info: &Info, game: &impl GameBackend) {
+    let entries = match game.fighter_entry_count() {
+        Some(entries) => entries,
+        None => return,
+    };
+    let menu = game.current_menu();
+    let result_mode = game.is_result_mode();
+
+    if entries > 0 && !result_mode && menu != CONTROLS_MENU {
+        if !info.is_match() {
+            results::clear_match_result(info);
+            stats::reset(info);
+            timeline::reset(info);
+            clock::start(info);
+        }
+        if info.go_frame.load().is_none() {
+            ruleset::start(info);
+        }
+        let remaining_frames = game.remaining_time_as_frame();
+        clock::update(info, remaining_frames, game.is_ready_go(), game.match_mode());
+        info.remaining_frames.store(remaining_frames, Ordering::SeqCst);
+        info.friendly_fire.store(game.friendly_fire(), Ordering::SeqCst);
+        info.is_match.store(true, Ordering::SeqCst);
+    } else {
+        if entries > 0 && result_mode && menu != CONTROLS_MENU && info.match_end() == MatchEnd::None {
+            results::store_match_result(info, |port| game.placement(port));
+        }
+        if info.is_match() {
+            stats::end_match(info);
+            clock::stop(info);
+        }
+        info.remaining_frames.store(NO_REMAINING_FRAMES, Ordering::SeqCst);
+        info.is_match.store(false, Ordering::SeqCst);
+        for player in &info.players {
+            player.is_in_game.store(false, Ordering::SeqCst);
+        }
+    }
+
+    info.current_menu.store(menu, Ordering::SeqCst);
+    if entries > 0 && menu != CONTROLS_MENU {
+        info.is_results_screen.store(result_mode, Ordering::SeqCst);
+    }
+}
+
+/// Copies everything about a fighter into its player slot. Called when it enters, respawns or
+/// takes damage, through the handlers below.
+pub fn set_player_information(info: &Info, game: &impl GameBackend, fighter: &impl Fighter) {
+    let port = fighter.entry_id();
+    let player = match info.players.get(port) {
+        Some(player) => player,
+        None => return,
+    };
+
+    if game.fighter_entry_count().unwrap_or(0) > 0 {
+        player.hero_menu_selected.store(false, Ordering::SeqCst);
+        player.hero_menu_open.store(false, Ordering::SeqCst);
+    }
+    player.is_in_game.store(true, Ordering::SeqCst);
+    player.character.store(fighter.character() as u32, Ordering::SeqCst);
+    player.damage.store(fighter.damage(), Ordering::SeqCst);
+    player.stocks.store(fighter.stock_count(), Ordering::SeqCst);
+    player.self_destructs.store(fighter.self_destructs(), Ordering::SeqCst);
+    player.is_cpu.store(fighter.is_cpu(), Ordering::SeqCst);
+    player.skin.store(fighter.skin(), Ordering::SeqCst);
+    player.team.store(fighter.team());
+    player.name.store_str(Some(&game.player_tag(port)), Ordering::SeqCst);
+}
+
+/// A fighter entered the match or respawned.
+pub fn handle_stock_start(info: &Info, game: &impl GameBackend, fighter: &impl Fighter) {
+    set_player_information(info, game, fighter);
+    stats::start_stock(info, fighter.entry_id());
+}
+
+/// A fighter took damage. What it took is the difference from the damage last seen.
+pub fn handle_damage(info: &Info, game: &impl GameBackend, fighter: &impl Fighter) {
+    let port = fighter.entry_id();
+    if let Some(player) = info.players.get(port) {
+        let taken = fighter.damage() - player.damage();
+        if info.is_match() && taken > 0.0 {
+            stats::record_damage(info, port, taken, fighter.last_attacker());
+        }
+    }
+    set_player_information(info, game, fighter);
+}
+
+/// A fighter is about to die. The game only takes the stock away afterwards, so the stock is
+/// counted here instead of waiting for the respawn.
+pub fn handle_pre_dead(info: &Info, fighter: &impl Fighter) {
+    let port = fighter.entry_id();
+    if let Some(player) = info.players.get(port) {
+        player.stocks.store(fighter.stock_count().saturating_sub(1), Ordering::SeqCst);
+        stats::record_fall(info, port, fighter.damage(), fighter.last_attacker());
+        timeline::record(info, port, fighter.damage(), fighter.last_attacker(), fighter.ko_type());
+    }
+}
+
+/// Called every frame for every fighter, to catch star and screen KOs as they happen.
+pub fn handle_fighter_frame(info: &Info, fighter: &impl Fighter) {
+    timeline::track_depth(info, fighter.entry_id(), fighter.depth());
+}
+
+/// Turns the per-fighter frame callback into one tick per game frame. Every fighter object runs
+/// the callback once a frame, so an object coming around again means a new frame has begun. That
+/// holds whichever fighters are still in, and with Nana running as a second object on Popo's port.
+pub struct FrameTicker {
+    // objects that have run since the last tick
+    seen: Mutex<Vec<usize>>,
+}
+
+impl FrameTicker {
+    pub const fn new() -> Self {
+        Self { seen: Mutex::new(Vec::new()) }
+    }
+
+    /// `object` tells fighter objects apart, e.g. by address. Returns whether a new frame began.
+    pub fn tick(&self, object: usize) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        let new_frame = seen.contains(&object);
+        if new_frame {
+            seen.clear();
+        }
+        seen.push(object);
+        new_frame
+    }
+}
+
+impl Default for FrameTicker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod backend_tests {
+    use super::*;
+    use crate::ruleset::Ruleset;
+    use crate::stats::Stats;
+    use crate::timeline::StockLoss;
+
+    struct FakeGame {
+        entries: Option<u32>,
+        result_mode: bool,
+        menu: u32,
+        remaining: u32,
+        placements: [u32; 2],
+        mode: MatchMode,
+        ready_go: bool,
+    }
+
+    impl Default for FakeGame {
+        fn default() -> Self {
+            Self { entries: Some(2), result_mode: false, menu: 0x6030000, remaining: 7 * 60 * 60, placements: [2, 1], mode: MatchMode::Stock, ready_go: true }
+        }
+    }
+
+    impl GameBackend for FakeGame {
+        fn fighter_entry_count(&self) -> Option<u32> {
+            self.entries
+        }
+
+        fn is_result_mode(&self) -> bool {
+            self.result_mode
+        }
+
+        fn current_menu(&self) -> u32 {
+            self.menu
+        }
+
+        fn remaining_time_as_frame(&self) -> u32 {
+            self.remaining
+        }
+
+        fn player_tag(&self, port: usize) -> String {
+            format!("P{}", port + 1)
+        }
+
+        fn placement(&self, port: usize) -> Option<u32> {
+            self.placements.get(port).copied()
+        }
+
+        fn friendly_fire(&self) -> bool {
+            true
+        }
+
+        fn is_ready_go(&self) -> bool {
+            self.ready_go
+        }
+
+        fn match_mode(&self) -> MatchMode {
+            self.mode
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct FakeFighter {
+        entry_id: usize,
+        stocks: u32,
+        damage: f32,
+        attacker: Option<usize>,
+        ko: KoType,
+        depth: f32,
+    }
+
+    impl FakeFighter {
+        fn new(entry_id: usize, stocks: u32, damage: f32) -> Self {
+            Self { entry_id, stocks, damage, attacker: None, ko: KoType::Left, depth: 0.0 }
+        }
+    }
+
+    impl Fighter for FakeFighter {
+        fn entry_id(&self) -> usize {
+            self.entry_id
+        }
+
+        fn character(&self) -> Character {
+            Character::Brave
+        }
+
+        fn damage(&self) -> f32 {
+            self.damage
+        }
+
+        fn stock_count(&self) -> u32 {
+            self.stocks
+        }
+
+        fn self_destructs(&self) -> u32 {
+            0
+        }
+
+        fn is_cpu(&self) -> bool {
+            false
+        }
+
+        fn skin(&self) -> u32 {
+            3
+        }
+
+        fn last_attacker(&self) -> Option<usize> {
+            self.attacker
+        }
+
+        fn ko_type(&self) -> KoType {
+            self.ko
+        }
+
+        fn depth(&self) -> f32 {
+            self.depth
+        }
+
+        fn team(&self) -> Option<u32> {
+            Some(self.entry_id as u32 % 2)
+        }
+    }
+
+    #[test]
+    fn test_match() {
+        let info = Info::new();
+        info.players[0].is_in_game.store(true, Ordering::SeqCst);
+        update_match_state(&info, &FakeGame { mode: MatchMode::Time, ..Default::default() });
+        assert!(info.is_match() && !info.is_results_screen() && info.friendly_fire());
+        assert_eq!(info.remaining_frames(), 7 * 60 * 60);
+        assert_eq!(info.clock().remaining_frames, Some(7 * 60 * 60));
+        assert_eq!(info.clock().time_limit_frames, Some(7 * 60 * 60));
+        assert_eq!(info.clock().mode, MatchMode::Time);
+        assert_eq!(info.current_menu(), 0x6030000);
+        assert!(info.players[0].is_in_game());
+
+        update_match_state(&info, &FakeGame { result_mode: true, ..Default::default() });
+        assert!(!info.is_match() && info.is_results_screen());
+        assert_eq!(info.remaining_frames(), NO_REMAINING_FRAMES);
+        assert_eq!(info.clock().remaining_frames, None);
+        assert!(!info.players[0].is_in_game());
+    }
+
+    #[test]
+    fn test_match_result() {
+        let info = Info::new();
+        let fighter = FakeFighter::new(1, 1, 0.0);
+        set_player_information(&info, &FakeGame::default(), &fighter);
+        set_player_information(&info, &FakeGame::default(), &FakeFighter { entry_id: 0, ..fighter });
+        handle_pre_dead(&info, &FakeFighter { entry_id: 0, ..fighter });
+        update_match_state(&info, &FakeGame::default());
+
+        update_match_state(&info, &FakeGame { result_mode: true, ..Default::default() });
+        let result = info.snapshot().match_result().unwrap();
+        assert_eq!((result.winners, result.end), (vec![1], MatchEnd::Stocks));
+
+        // read once; the players are out of the game by the next poll
+        update_match_state(&info, &FakeGame { result_mode: true, placements: [1, 1], ..Default::default() });
+        assert_eq!(info.players[1].placement(), 1);
+        assert_eq!(info.players[0].placement(), 2);
+
+        update_match_state(&info, &FakeGame::default());
+        assert_eq!(info.snapshot().match_result(), None);
+    }
+
+    #[test]
+    fn test_frame_ticker() {
+        let ticker = FrameTicker::new();
+        let ticks = |objects: &[usize]| objects.iter().filter(|&&object| ticker.tick(object)).count();
+        // Popo, Nana and Mario
+        assert_eq!(ticks(&[10, 11, 20]), 0);
+        assert_eq!(ticks(&[10, 11, 20]), 1);
+        // the order fighters run in may change
+        assert_eq!(ticks(&[20, 11, 10]), 1);
+        // Mario is out, and the Ice Climbers keep the frames going
+        assert_eq!(ticks(&[10, 11, 10, 11]), 2);
+    }
+
+    #[test]
+    fn test_controls_screen_isnt_a_match() {
+        let info = Info::new();
+        update_match_state(&info, &FakeGame { menu: CONTROLS_MENU, result_mode: true, ..Default::default() });
+        assert!(!info.is_match());
+        // nor does it touch the results screen flag
+        assert!(!info.is_results_screen());
+        assert_eq!(info.current_menu(), CONTROLS_MENU);
+    }
+
+    #[test]
+    fn test_no_fighter_manager() {
+        let info = Info::new();
+        update_match_state(&info, &FakeGame { entries: None, ..Default::default() });
+        assert_eq!(info.snapshot(), Info::new().snapshot());
+
+        update_match_state(&info, &FakeGame { entries: Some(0), ..Default::default() });
+        assert!(!info.is_match());
+        assert_eq!(info.current_menu(), 0x6030000);
+    }
+
+    #[test]
+    fn test_set_player_information() {
+        let info = Info::new();
+        info.players[1].hero_menu_open.store(true, Ordering::SeqCst);
+        set_player_information(&info, &FakeGame::default(), &FakeFighter::new(1, 3, 12.5));
+
+        let player = info.players[1].snapshot();
+        assert!(player.is_in_game && !player.hero_menu_open);
+        assert_eq!((player.character, player.skin, player.stocks, player.damage), (Character::Brave, 3, 3, 12.5));
+        assert_eq!((player.name.as_deref(), player.team), (Some("P2"), Some(1)));
+
+        // entry ids past the last port are ignored rather than indexing out of bounds
+        set_player_information(&info, &FakeGame::default(), &FakeFighter::new(8, 3, 0.0));
+    }
+
+    #[test]
+    fn test_pre_dead() {
+        let info = Info::new();
+        let fighter = FakeFighter::new(0, 3, 80.0);
+        set_player_information(&info, &FakeGame::default(), &fighter);
+        handle_pre_dead(&info, &fighter);
+        assert_eq!(info.players[0].stocks(), 2);
+
+        // the last stock can't go below zero
+        handle_pre_dead(&info, &FakeFighter { stocks: 0, ..fighter });
+        assert_eq!(info.players[0].stocks(), 0);
+    }
+
+    #[test]
+    fn test_ruleset() {
+        let info = Info::new();
+        let game = FakeGame { ready_go: false, ..Default::default() };
+        let fighter = FakeFighter::new(0, 3, 0.0);
+        handle_stock_start(&info, &game, &fighter);
+        handle_stock_start(&info, &game, &FakeFighter { entry_id: 1, ..fighter });
+        update_match_state(&info, &game);
+        assert_eq!(info.ruleset(), Some(Ruleset { stocks: Some(3) }));
+
+        // after GO, losing stocks doesn't change the rules
+        update_match_state(&info, &FakeGame::default());
+        handle_pre_dead(&info, &fighter);
+        update_match_state(&info, &FakeGame::default());
+        assert_eq!(info.players[0].stocks(), 2);
+        assert_eq!(info.ruleset(), Some(Ruleset { stocks: Some(3) }));
+    }
+
+    #[test]
+    fn test_star_ko() {
+        let info = Info::new();
+        let game = FakeGame::default();
+        let fighter = FakeFighter { ko: KoType::Top, ..FakeFighter::new(0, 3, 130.0) };
+        handle_stock_start(&info, &game, &fighter);
+        update_match_state(&info, &game);
+
+        handle_pre_dead(&info, &fighter);
+        handle_fighter_frame(&info, &fighter);
+        info.frame.store(30, Ordering::SeqCst);
+        handle_fighter_frame(&info, &FakeFighter { depth: -120.0, ..fighter });
+        assert_eq!(info.stock_losses()[0].ko, KoType::Star);
+    }
+
+    #[test]
+    fn test_stats() {
+        let info = Info::new();
+        let game = FakeGame::default();
+        let mario = FakeFighter::new(0, 2, 0.0);
+        let link = FakeFighter::new(1, 2, 0.0);
+        handle_stock_start(&info, &game, &mario);
+        handle_stock_start(&info, &game, &link);
+        update_match_state(&info, &game);
+
+        handle_damage(&info, &game, &FakeFighter { damage: 14.0, attacker: Some(0), ..link });
+        // the same hit reported again adds nothing
+        handle_damage(&info, &game, &FakeFighter { damage: 14.0, attacker: Some(0), ..link });
+        handle_damage(&info, &game, &FakeFighter { damage: 9.0, attacker: Some(1), ..mario });
+        info.frame.store(300, Ordering::SeqCst);
+        handle_pre_dead(&info, &FakeFighter { damage: 14.0, attacker: Some(0), ..link });
+        handle_stock_start(&info, &game, &FakeFighter { stocks: 1, ..link });
+        info.frame.store(400, Ordering::SeqCst);
+        update_match_state(&info, &FakeGame { result_mode: true, ..Default::default() });
+
+        let (mario, link) = (info.players[0].stats(), info.players[1].stats());
+        assert_eq!((mario.damage_dealt, mario.damage_taken, mario.kos), (14.0, 9.0, 1));
+        assert_eq!((mario.average_ko_percent, mario.longest_stock_frames), (Some(14.0), 400));
+        assert_eq!((link.damage_dealt, link.falls, link.longest_stock_frames), (9.0, 1, 300));
+        assert_eq!(info.stock_losses(), [
+            StockLoss { port: 1, frame: 300, percent: 14.0, killer: Some(0), ko: KoType::Left },
+        ]);
+
+        // a new match starts from nothing
+        update_match_state(&info, &game);
+        assert_eq!(info.players[0].stats(), Stats::default());
+        assert!(info.stock_losses().is_empty());
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::VecDeque;
+use std::io;
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, ToSocketAddrs};
+
+use super::{Backoff, ClientError, Options, StreamState, Update};
+use crate::InfoSnapshot;
+use crate::protocol::{self, Command};
+use crate::sets::{SetState, SetTracker};
+
+/// [`Client`](super::Client) for tokio. Behaves the same, just without blocking a thread.
+pub struct AsyncClient {
+    addrs: Vec<SocketAddr>,
+    options: Options,
+    stream: Option<TcpStream>,
+    state: StreamState,
+    backoff: Backoff,
+    info: Option<InfoSnapshot>,
+    sets: SetTracker,
+    pending: VecDeque<Update>,
+    idle: u32,
+}
+
+impl AsyncClient {
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self, ClientError> {
+        Self::connect_with(addr, Options::default()).await
+    }
+
+    pub async fn connect_with(addr: impl ToSocketAddrs, options: Options) -> Result<Self, ClientError> {
+        let addrs: Vec<_> = tokio::net::lookup_host(addr).await?.collect();
+        let backoff = Backoff::new(options.min_backoff, options.max_backoff);
+        let mut client = Self {
+            addrs,
+            options,
+            stream: None,
+            state: StreamState::default(),
+            backoff,
+            info: None,
+            sets: SetTracker::new(),
+            pending: VecDeque::new(),
+            idle: 0,
+        };
+        client.open().await?;
+        Ok(client)
+    }
+
+    pub fn info(&self) -> Option<&InfoSnapshot> {
+        self.info.as_ref()
+    }
+
+    pub fn set(&self) -> Option<&SetState> {
+        self.sets.state()
+    }
+
+    pub fn set_best_of(&mut self, best_of: Option<u32>) {
+        self.sets.set_best_of(best_of);
+    }
+
+    pub async fn send(&mut self, command: &Command) -> Result<(), ClientError> {
+        match &mut self.stream {
+            Some(stream) => Ok(stream.write_all(&protocol::encode(command)).await?),
+            None => Err(ClientError::Closed),
+        }
+    }
+
+    pub async fn next_update(&mut self) -> Result<Update, ClientError> {
+        let mut buf = [0u8; 0x1000];
+        loop {
+            if let Some(update) = self.pending.pop_front() {
+                return Ok(update);
+            }
+
+            if self.stream.is_none() {
+                self.reconnect().await?;
+            }
+
+            let mut out = Vec::new();
+            let next = self.state.next_update(&self.options.hello, &mut out);
+            for command in &out {
+                self.send_or_disconnect(command).await?;
+                if self.stream.is_none() {
+                    break;
+                }
+            }
+            match next {
+                Some(Ok(update)) => {
+                    if let Update::Info(info) = &update {
+                        self.info = Some((**info).clone());
+                        if self.sets.update(info) {
+                            self.pending.extend(self.sets.state().cloned().map(Update::Set));
+                        }
+                    }
+                    return Ok(update);
+                }
+                Some(Err(err)) => return Err(ClientError::Frame(err)),
+                None => (),
+            }
+
+            let stream = match self.stream.as_mut() {
+                Some(stream) => stream,
+                None => continue,
+            };
+            let read = match self.options.stale_after {
+                Some(stale) => tokio::time::timeout(stale / 2, stream.read(&mut buf)).await
+                    .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into())),
+                None => stream.read(&mut buf).await,
+            };
+
+            match read {
+                Ok(0) => self.disconnect(ClientError::Closed)?,
+                Ok(len) => {
+                    self.idle = 0;
+                    self.state.extend(&buf[..len]);
+                }
+                Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                    self.idle += 1;
+                    if self.idle >= 2 {
+                        self.disconnect(ClientError::Stale)?;
+                    } else {
+                        self.state.pings += 1;
+                        self.send_or_disconnect(&Command::Ping).await?;
+                    }
+                }
+                Err(err) => self.disconnect(ClientError::Io(err))?,
+            }
+        }
+    }
+
+    async fn open(&mut self) -> Result<(), ClientError> {
+        self.stream = Some(TcpStream::connect(&self.addrs[..]).await?);
+        self.state = StreamState::default();
+        self.idle = 0;
+        Ok(())
+    }
+
+    async fn reconnect(&mut self) -> Result<(), ClientError> {
+        loop {
+            match self.open().await {
+                Ok(()) => {
+                    self.backoff.reset();
+                    return Ok(());
+                }
+                Err(err) if !self.options.reconnect => return Err(err),
+                Err(_) => tokio::time::sleep(self.backoff.next_delay()).await,
+            }
+        }
+    }
+
+    async fn send_or_disconnect(&mut self, command: &Command) -> Result<(), ClientError> {
+        match self.send(command).await {
+            Err(ClientError::Io(err)) => self.disconnect(ClientError::Io(err)),
+            result => result,
+        }
+    }
+
+    fn disconnect(&mut self, reason: ClientError) -> Result<(), ClientError> {
+        self.stream = None;
+        if !self.options.reconnect {
+            return Err(reason);
+        }
+        self.pending.push_back(Update::Disconnected { reason: reason.to_string() });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod async_client_tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use crate::events::Event;
+    use crate::protocol::ServerMessage;
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for _ in 0..2 {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                socket.write_all(&protocol::encode(&ServerMessage::Event { event: Event::MatchEnded })).await.unwrap();
+            }
+        });
+
+        let options = Options { min_backoff: Duration::from_millis(10), ..Default::default() };
+        let mut client = AsyncClient::connect_with(addr, options).await.unwrap();
+        assert_eq!(client.next_update().await.unwrap(), Update::Event(Event::MatchEnded));
+        assert!(matches!(client.next_update().await.unwrap(), Update::Disconnected { .. }));
+        assert_eq!(client.next_update().await.unwrap(), Update::Event(Event::MatchEnded));
+    }
+}
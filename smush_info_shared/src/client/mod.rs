@@ -0,0 +1,520 @@
+//! A ready-made client for the plugin's stream, behind the `client` feature (and `async-client`
+//! for [`AsyncClient`]).
+//!
+//! ```no_run
+//! use smush_info_shared::client::{Client, Update};
+//!
+//! let mut client = Client::connect("192.168.1.20:4242").unwrap();
+//! while let Ok(update) = client.next_update() {
+//!     match update {
+//!         Update::Info(info) => println!("{} on {:?}", info.is_match, info.stage),
+//!         Update::Event(event) => println!("{:?}", event),
+//!         _ => (),
+//!     }
+//! }
+//! ```
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::InfoSnapshot;
+use crate::delta::DeltaDecoder;
+use crate::events::Event;
+use crate::handshake::{ClientHello, Hello};
+use crate::protocol::{self, Command, FrameBuffer, FrameError, Reply, ServerMessage};
+use crate::sets::{SetState, SetTracker};
+
+#[cfg(feature = "async-client")]
+mod async_client;
+#[cfg(feature = "async-client")]
+pub use async_client::AsyncClient;
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Sent in answer to the server's hello on every (re)connect. Nothing is sent when default.
+    pub hello: ClientHello,
+    /// How long the server may go quiet before the connection counts as dead. A ping is sent
+    /// halfway through. `None` waits forever.
+    pub stale_after: Option<Duration>,
+    /// Reconnect after the connection drops instead of returning an error.
+    pub reconnect: bool,
+    pub min_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            hello: ClientHello::default(),
+            stale_after: Some(Duration::from_secs(5)),
+            reconnect: true,
+            min_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Update {
+    /// A (re)connect finished; the server's hello.
+    Connected(Hello),
+    /// The latest state, whether it arrived whole or as a patch.
+    Info(Box<InfoSnapshot>),
+    Event(Event),
+    Reply(Reply),
+    /// The set being played changed, following the [`Update::Info`] that changed it.
+    Set(SetState),
+    /// The connection dropped and the client is about to reconnect.
+    Disconnected { reason: String },
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(io::Error),
+    /// A message couldn't be decoded. The connection is still usable.
+    Frame(FrameError),
+    /// Nothing arrived for [`Options::stale_after`], not even a pong.
+    Stale,
+    /// The server closed the connection.
+    Closed,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(err) => write!(f, "{}", err),
+            ClientError::Frame(err) => write!(f, "bad message: {:?}", err),
+            ClientError::Stale => write!(f, "server went quiet"),
+            ClientError::Closed => write!(f, "server closed the connection"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<io::Error> for ClientError {
+    fn from(err: io::Error) -> Self {
+        ClientError::Io(err)
+    }
+}
+
+/// Exponential backoff between reconnect attempts.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    min: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Self { min, max, next: min }
+    }
+
+    /// Returns how long to wait before the next attempt, doubling it for the one after.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.next = self.min;
+    }
+}
+
+/// Turns the bytes of one connection into [`Update`]s, independent of how they're read.
+struct StreamState {
+    frames: FrameBuffer,
+    decoder: DeltaDecoder,
+    // pongs for our own keepalive pings aren't passed on
+    pings: u32,
+}
+
+impl Default for StreamState {
+    fn default() -> Self {
+        Self { frames: FrameBuffer::with_max_len(protocol::MAX_SERVER_FRAME_LEN), decoder: DeltaDecoder::default(), pings: 0 }
+    }
+}
+
+impl StreamState {
+    fn extend(&mut self, bytes: &[u8]) {
+        self.frames.extend(bytes);
+    }
+
+    /// Pops the next update. Commands the client has to send in response go into `out`.
+    fn next_update(&mut self, hello: &ClientHello, out: &mut Vec<Command>) -> Option<Result<Update, FrameError>> {
+        loop {
+            let frame = match self.frames.next_frame()? {
+                Ok(frame) => frame,
+                Err(err) => return Some(Err(err)),
+            };
+            let encoding = self.frames.encoding();
+            let message = match protocol::decode_server_message_as(encoding, &frame) {
+                Ok(message) => message,
+                Err(err) => return Some(Err(err)),
+            };
+
+            let update = match message {
+                ServerMessage::Hello { hello: server_hello } => {
+                    if *hello != ClientHello::default() {
+                        out.push(Command::Hello(hello.clone()));
+                    }
+                    Update::Connected(server_hello)
+                }
+                ServerMessage::Event { event } => Update::Event(event),
+                ServerMessage::Reply(Reply::Pong) if self.pings > 0 => {
+                    self.pings -= 1;
+                    continue
+                }
+                ServerMessage::Reply(reply) => {
+                    if let Reply::Welcome(ClientHello { encoding: Some(encoding), .. }) = &reply {
+                        self.frames.set_encoding(*encoding);
+                    }
+                    Update::Reply(reply)
+                }
+                ServerMessage::Delta(delta) => match self.decoder.apply(delta) {
+                    Ok(info) => Update::Info(Box::new(info.clone())),
+                    // a patch went missing; asking for diff mode again gets a fresh full snapshot
+                    Err(_) => {
+                        out.push(Command::SetDelta { enabled: true });
+                        continue
+                    }
+                },
+                ServerMessage::Info(info) => Update::Info(info),
+            };
+            return Some(Ok(update));
+        }
+    }
+}
+
+/// Blocking client. Reconnects on its own unless [`Options::reconnect`] is off.
+pub struct Client {
+    addrs: Vec<SocketAddr>,
+    options: Options,
+    stream: Option<TcpStream>,
+    state: StreamState,
+    backoff: Backoff,
+    info: Option<InfoSnapshot>,
+    // kept across reconnects, so a dropped connection mid-set doesn't lose the score
+    sets: SetTracker,
+    pending: VecDeque<Update>,
+    // read timeouts in a row; the first sends a ping, the second gives up
+    idle: u32,
+}
+
+impl Client {
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self, ClientError> {
+        Self::connect_with(addr, Options::default())
+    }
+
+    /// Connects once up front, so a wrong address fails here rather than retrying forever.
+    pub fn connect_with(addr: impl ToSocketAddrs, options: Options) -> Result<Self, ClientError> {
+        let addrs: Vec<_> = addr.to_socket_addrs()?.collect();
+        let backoff = Backoff::new(options.min_backoff, options.max_backoff);
+        let mut client = Self {
+            addrs,
+            options,
+            stream: None,
+            state: StreamState::default(),
+            backoff,
+            info: None,
+            sets: SetTracker::new(),
+            pending: VecDeque::new(),
+            idle: 0,
+        };
+        client.open()?;
+        Ok(client)
+    }
+
+    /// The most recent info received, if any.
+    pub fn info(&self) -> Option<&InfoSnapshot> {
+        self.info.as_ref()
+    }
+
+    /// The set being played, or the last one until the next match starts. Changes arrive as
+    /// [`Update::Set`].
+    pub fn set(&self) -> Option<&SetState> {
+        self.sets.state()
+    }
+
+    /// See [`SetTracker::set_best_of`]. Takes effect on the current set without an update.
+    pub fn set_best_of(&mut self, best_of: Option<u32>) {
+        self.sets.set_best_of(best_of);
+    }
+
+    /// Sends a command. Its reply arrives as an [`Update::Reply`].
+    pub fn send(&mut self, command: &Command) -> Result<(), ClientError> {
+        match &mut self.stream {
+            Some(stream) => Ok(stream.write_all(&protocol::encode(command))?),
+            None => Err(ClientError::Closed),
+        }
+    }
+
+    /// Blocks until the next update. With reconnecting on, only [`ClientError::Frame`] is returned
+    /// and connection problems show up as [`Update::Disconnected`].
+    pub fn next_update(&mut self) -> Result<Update, ClientError> {
+        let mut buf = [0u8; 0x1000];
+        loop {
+            if let Some(update) = self.pending.pop_front() {
+                return Ok(update);
+            }
+
+            if self.stream.is_none() {
+                self.reconnect()?;
+            }
+
+            let mut out = Vec::new();
+            let next = self.state.next_update(&self.options.hello, &mut out);
+            for command in &out {
+                self.send_or_disconnect(command)?;
+                if self.stream.is_none() {
+                    break;
+                }
+            }
+            match next {
+                Some(Ok(update)) => {
+                    if let Update::Info(info) = &update {
+                        self.info = Some((**info).clone());
+                        if self.sets.update(info) {
+                            self.pending.extend(self.sets.state().cloned().map(Update::Set));
+                        }
+                    }
+                    return Ok(update);
+                }
+                Some(Err(err)) => return Err(ClientError::Frame(err)),
+                None => (),
+            }
+
+            let stream = match self.stream.as_mut() {
+                Some(stream) => stream,
+                // the handshake failed to send
+                None => continue,
+            };
+            match stream.read(&mut buf) {
+                Ok(0) => self.disconnect(ClientError::Closed)?,
+                Ok(len) => {
+                    self.idle = 0;
+                    self.state.extend(&buf[..len]);
+                }
+                Err(err) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                    self.idle += 1;
+                    if self.idle >= 2 {
+                        self.disconnect(ClientError::Stale)?;
+                    } else {
+                        self.state.pings += 1;
+                        self.send_or_disconnect(&Command::Ping)?;
+                    }
+                }
+                Err(err) => self.disconnect(ClientError::Io(err))?,
+            }
+        }
+    }
+
+    fn open(&mut self) -> Result<(), ClientError> {
+        let stream = TcpStream::connect(&self.addrs[..])?;
+        stream.set_read_timeout(self.options.stale_after.map(|stale| stale / 2))?;
+        self.stream = Some(stream);
+        self.state = StreamState::default();
+        self.idle = 0;
+        Ok(())
+    }
+
+    fn reconnect(&mut self) -> Result<(), ClientError> {
+        loop {
+            match self.open() {
+                Ok(()) => {
+                    self.backoff.reset();
+                    return Ok(());
+                }
+                Err(err) if !self.options.reconnect => return Err(err),
+                Err(_) => std::thread::sleep(self.backoff.next_delay()),
+            }
+        }
+    }
+
+    /// Sends a command of the client's own. Failing to write counts as a dropped connection,
+    /// the same as failing to read.
+    fn send_or_disconnect(&mut self, command: &Command) -> Result<(), ClientError> {
+        match self.send(command) {
+            Err(ClientError::Io(err)) => self.disconnect(ClientError::Io(err)),
+            result => result,
+        }
+    }
+
+    /// Drops the connection, queueing an [`Update::Disconnected`] if it'll be reopened.
+    fn disconnect(&mut self, reason: ClientError) -> Result<(), ClientError> {
+        self.stream = None;
+        if !self.options.reconnect {
+            return Err(reason);
+        }
+        self.pending.push_back(Update::Disconnected { reason: reason.to_string() });
+        Ok(())
+    }
+}
+
+impl Iterator for Client {
+    type Item = Result<Update, ClientError>;
+
+    /// Ends once the connection is gone for good, i.e. only when not reconnecting.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stream.is_none() && !self.options.reconnect {
+            return None;
+        }
+        Some(self.next_update())
+    }
+}
+
+#[cfg(test)]
+mod client_tests {
+    use super::*;
+    use std::net::TcpListener;
+    use crate::Stage;
+    use crate::protocol::Encoding;
+
+    fn hello() -> Hello {
+        Hello {
+            plugin_version: "0.3.0".to_owned(),
+            schema_version: crate::handshake::SCHEMA_VERSION,
+            game_version: None,
+            hooks: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(300));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(200));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(300));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(300));
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_stream_state() {
+        let client_hello = ClientHello { delta: Some(true), encoding: Some(Encoding::MessagePack), ..Default::default() };
+        let mut state = StreamState::default();
+        let mut out = Vec::new();
+
+        state.extend(&protocol::encode(&ServerMessage::Hello { hello: hello() }));
+        assert_eq!(state.next_update(&client_hello, &mut out).unwrap().unwrap(), Update::Connected(hello()));
+        assert_eq!(out, [Command::Hello(client_hello.clone())]);
+        out.clear();
+
+        // everything after the welcome is MessagePack
+        state.extend(&protocol::encode(&Reply::Welcome(client_hello.clone())));
+        let mut info = InfoSnapshot { stage: Stage::Battle_DK_Jungle, ..Default::default() };
+        let mut encoder = crate::delta::DeltaEncoder::new();
+        state.extend(&protocol::encode_as(Encoding::MessagePack, &encoder.encode(&info).unwrap()));
+        info.is_match = true;
+        let _lost = encoder.encode(&info);
+        info.players[0].stocks = 3;
+        state.extend(&protocol::encode_as(Encoding::MessagePack, &encoder.encode(&info).unwrap()));
+
+        assert!(matches!(state.next_update(&client_hello, &mut out), Some(Ok(Update::Reply(Reply::Welcome(_))))));
+        assert!(matches!(state.next_update(&client_hello, &mut out), Some(Ok(Update::Info(i))) if i.stage == Stage::Battle_DK_Jungle));
+
+        // the skipped patch is dropped and a new baseline requested
+        assert!(state.next_update(&client_hello, &mut out).is_none());
+        assert_eq!(out, [Command::SetDelta { enabled: true }]);
+    }
+
+    #[test]
+    fn test_long_timeline() {
+        // a long time battle's info is past the limit on command lines, and still comes through
+        let loss = crate::timeline::StockLoss { port: 0, frame: 30000, percent: 120.0, killer: Some(1), ko: crate::timeline::KoType::Top };
+        let info = InfoSnapshot { stock_losses: vec![loss; 500], ..Default::default() };
+        let frame = protocol::encode(&info);
+        assert!(frame.len() > protocol::MAX_FRAME_LEN);
+
+        let mut state = StreamState::default();
+        state.extend(&frame);
+        let update = state.next_update(&ClientHello::default(), &mut Vec::new()).unwrap().unwrap();
+        assert!(matches!(update, Update::Info(i) if i.stock_losses.len() == 500));
+    }
+
+    #[test]
+    fn test_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            socket.write_all(&protocol::encode(&ServerMessage::Hello { hello: hello() })).unwrap();
+            socket.write_all(&protocol::encode(&InfoSnapshot::default())).unwrap();
+            socket.write_all(&protocol::encode(&ServerMessage::Event { event: Event::MatchEnded })).unwrap();
+
+            // answer the keepalive ping, then go quiet
+            let mut buf = [0u8; 64];
+            let len = socket.read(&mut buf).unwrap();
+            assert_eq!(protocol::decode_command(&buf[..len - 1]).unwrap(), Command::Ping);
+            socket.write_all(&protocol::encode(&Reply::Pong)).unwrap();
+            std::thread::sleep(Duration::from_millis(500));
+        });
+
+        let options = Options {
+            stale_after: Some(Duration::from_millis(200)),
+            reconnect: false,
+            ..Default::default()
+        };
+        let mut client = Client::connect_with(addr, options).unwrap();
+        assert_eq!(client.next_update().unwrap(), Update::Connected(hello()));
+        assert!(matches!(client.next_update().unwrap(), Update::Info(_)));
+        assert_eq!(client.info(), Some(&InfoSnapshot::default()));
+        assert_eq!(client.next_update().unwrap(), Update::Event(Event::MatchEnded));
+        assert!(matches!(client.next_update(), Err(ClientError::Stale)));
+        assert!(client.next().is_none());
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_client_sets() {
+        // a lobby, then a game that port 0 wins
+        let mut info = InfoSnapshot::default();
+        let mut infos = vec![info.clone()];
+        info.is_match = true;
+        for player in &mut info.players[..2] {
+            player.is_in_game = true;
+            player.stocks = 1;
+        }
+        infos.push(info.clone());
+        info.players[1].stocks = 0;
+        infos.push(info.clone());
+        info.is_match = false;
+        for player in &mut info.players[..2] {
+            player.is_in_game = false;
+        }
+        infos.push(info);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            for info in &infos {
+                socket.write_all(&protocol::encode(info)).unwrap();
+            }
+        });
+
+        let options = Options { reconnect: false, ..Default::default() };
+        let mut client = Client::connect_with(addr, options).unwrap();
+        client.set_best_of(Some(1));
+        let mut sets = Vec::new();
+        for update in client.by_ref().take(6) {
+            if let Update::Set(set) = update.unwrap() {
+                sets.push(set);
+            }
+        }
+
+        // one when the game starts, one when it's won
+        assert_eq!(sets.len(), 2);
+        assert_eq!((sets[0].best_of, sets[0].game, sets[0].winner), (1, 1, None));
+        assert_eq!(sets[1].winner, Some(0));
+        assert_eq!(client.set(), Some(&sets[1]));
+
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,273 @@
+//! The match clock and mode, from the game's remaining time.
+use serde::{Serialize, Deserialize};
+
+use crate::Info;
+use crate::results::SUDDEN_DEATH_DAMAGE;
+
+/// Longest time limit the game offers. Larger remaining times mean there's no limit.
+pub const MAX_TIME_LIMIT_FRAMES: u32 = 99 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    #[default]
+    Stock,
+    Time,
+    Stamina,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Clock {
+    /// `None` outside of a match, or without a time limit.
+    pub remaining_frames: Option<u32>,
+    pub time_limit_frames: Option<u32>,
+    /// Game frames since GO. Stops when the match ends.
+    pub elapsed_frames: u32,
+    /// Of the current or last match.
+    pub mode: MatchMode,
+    /// Time ran out on a tie and the tied players are fighting it out at 300%.
+    pub sudden_death: bool,
+}
+
+impl Clock {
+    pub const fn new() -> Self {
+        Self {
+            remaining_frames: None,
+            time_limit_frames: None,
+            elapsed_frames: 0,
+            mode: MatchMode::Stock,
+            sudden_death: false,
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A match is loading. The time limit is taken from the first [`update`].
+pub fn start(info: &Info) {
+    info.clock.store(Clock::default());
+    info.go_frame.store(None);
+}
+
+/// Called while the match is on with the game's remaining time (which holds the full time limit
+/// until GO), whether GO has happened and the mode. The mode is kept as of GO, when every fighter
+/// is in.
+pub fn update(info: &Info, remaining_frames: u32, is_ready_go: bool, mode: MatchMode) {
+    let before_go = info.go_frame.load().is_none();
+    if before_go && is_ready_go {
+        info.go_frame.store(Some(info.frame()));
+    }
+    let elapsed_frames = elapsed(info);
+
+    let mode = if before_go { mode } else { info.clock().mode };
+    let tied = tied_ports(info, mode);
+    let tied_at_sudden_death = !tied.is_empty()
+        && tied.iter().all(|&port| info.players[port].damage() >= SUDDEN_DEATH_DAMAGE);
+
+    info.clock.modify(|clock| {
+        if before_go {
+            clock.mode = mode;
+            if clock.time_limit_frames.is_none() && (1..=MAX_TIME_LIMIT_FRAMES).contains(&remaining_frames) {
+                clock.time_limit_frames = Some(remaining_frames);
+            }
+        }
+        let timed = clock.time_limit_frames.is_some();
+        clock.remaining_frames = if timed { Some(remaining_frames) } else { None };
+        clock.elapsed_frames = elapsed_frames;
+        if timed && remaining_frames == 0 && tied_at_sudden_death {
+            clock.sudden_death = true;
+        }
+    });
+}
+
+/// The ports that would play sudden death if time ran out now: the players on every side (team,
+/// or player outside team battles) sharing the best score, if more than one side does. The score
+/// is stocks left, or KOs minus falls in time battles, where nobody has stocks. Empty without a
+/// tie.
+pub fn tied_ports(info: &Info, mode: MatchMode) -> Vec<usize> {
+    let time_battle = mode == MatchMode::Time;
+    let mut sides: Vec<(Result<u32, usize>, i64)> = Vec::new();
+    for (port, player) in info.players.iter().enumerate().filter(|(_, player)| player.is_in_game()) {
+        let side = player.team().ok_or(port);
+        let score = if time_battle {
+            let stats = player.stats();
+            stats.kos as i64 - stats.falls as i64
+        } else {
+            player.stocks() as i64
+        };
+        match sides.iter_mut().find(|(s, _)| *s == side) {
+            Some((_, total)) => *total += score,
+            None => sides.push((side, score)),
+        }
+    }
+
+    let best = match sides.iter().map(|&(_, score)| score).max() {
+        Some(best) => best,
+        None => return Vec::new(),
+    };
+    let tied: Vec<_> = sides.into_iter().filter(|&(_, score)| score == best).map(|(side, _)| side).collect();
+    if tied.len() < 2 {
+        return Vec::new();
+    }
+    // teammates who ran out of stocks sit it out with everyone else
+    info.players.iter().enumerate()
+        .filter(|&(port, player)| {
+            player.is_in_game()
+                && tied.contains(&player.team().ok_or(port))
+                && (time_battle || player.stocks() > 0)
+        })
+        .map(|(port, _)| port)
+        .collect()
+}
+
+/// Game frames since GO, or 0 before it.
+pub fn elapsed(info: &Info) -> u32 {
+    info.go_frame.load().map_or(0, |go| info.frame().wrapping_sub(go))
+}
+
+/// The match is over; the clock keeps its last reading, minus the remaining time.
+pub fn stop(info: &Info) {
+    info.clock.modify(|clock| clock.remaining_frames = None);
+}
+
+#[cfg(test)]
+mod clock_tests {
+    use super::*;
+    use core::sync::atomic::Ordering;
+
+    #[test]
+    fn test_timed() {
+        let info = Info::new();
+        assert_eq!(info.clock(), Clock::default());
+
+        start(&info);
+        update(&info, 3 * 60 * 60, false, MatchMode::Time);
+        info.frame.store(200, Ordering::SeqCst);
+        update(&info, 3 * 60 * 60, true, MatchMode::Time);
+        info.frame.store(500, Ordering::SeqCst);
+        // past GO the mode stays put
+        update(&info, 3 * 60 * 60 - 300, true, MatchMode::Stock);
+        assert_eq!(info.clock(), Clock {
+            remaining_frames: Some(3 * 60 * 60 - 300),
+            time_limit_frames: Some(3 * 60 * 60),
+            elapsed_frames: 300,
+            mode: MatchMode::Time,
+            sudden_death: false,
+        });
+
+        stop(&info);
+        info.frame.store(900, Ordering::SeqCst);
+        let clock = info.clock();
+        assert_eq!((clock.remaining_frames, clock.elapsed_frames), (None, 300));
+    }
+
+    #[test]
+    fn test_untimed() {
+        let info = Info::new();
+        start(&info);
+        update(&info, u32::MAX, true, MatchMode::Stock);
+        info.frame.store(60, Ordering::SeqCst);
+        update(&info, u32::MAX, true, MatchMode::Stock);
+        let clock = info.clock();
+        assert_eq!((clock.remaining_frames, clock.time_limit_frames, clock.elapsed_frames), (None, None, 60));
+    }
+
+    #[test]
+    fn test_sudden_death() {
+        let info = Info::new();
+        for player in &info.players[..2] {
+            player.is_in_game.store(true, Ordering::SeqCst);
+            player.stocks.store(1, Ordering::SeqCst);
+        }
+        start(&info);
+        update(&info, 60, true, MatchMode::Stock);
+        // time just ran out; the results come next unless it's a tie
+        update(&info, 0, true, MatchMode::Stock);
+        assert!(!info.clock().sudden_death);
+
+        for player in &info.players[..2] {
+            player.damage.store(300.0, Ordering::SeqCst);
+        }
+        update(&info, 0, true, MatchMode::Stock);
+        assert!(info.clock().sudden_death);
+    }
+
+    #[test]
+    fn test_time_battle_sudden_death() {
+        // the game reports no stocks at all in time battles
+        let info = Info::new();
+        for player in &info.players[..2] {
+            player.is_in_game.store(true, Ordering::SeqCst);
+        }
+        start(&info);
+        update(&info, 60, true, MatchMode::Time);
+        update(&info, 0, true, MatchMode::Time);
+        assert!(!info.clock().sudden_death);
+
+        for player in &info.players[..2] {
+            player.damage.store(300.0, Ordering::SeqCst);
+        }
+        update(&info, 0, true, MatchMode::Time);
+        assert!(info.clock().sudden_death);
+    }
+
+    #[test]
+    fn test_free_for_all_sudden_death() {
+        // ports 0 and 1 are tied on two stocks; port 2 is behind and doesn't play sudden death
+        let info = Info::new();
+        for (player, stocks) in info.players.iter().zip([2, 2, 1]) {
+            player.is_in_game.store(true, Ordering::SeqCst);
+            player.stocks.store(stocks, Ordering::SeqCst);
+        }
+        start(&info);
+        update(&info, 60, true, MatchMode::Stock);
+        assert_eq!(tied_ports(&info, MatchMode::Stock), [0, 1]);
+
+        for player in &info.players[..2] {
+            player.damage.store(300.0, Ordering::SeqCst);
+        }
+        update(&info, 0, true, MatchMode::Stock);
+        assert!(info.clock().sudden_death);
+    }
+
+    #[test]
+    fn test_time_battle_leader() {
+        // a time battle goes to whoever has the most KOs minus falls, whatever their damage
+        let info = Info::new();
+        for player in &info.players[..3] {
+            player.is_in_game.store(true, Ordering::SeqCst);
+            player.damage.store(300.0, Ordering::SeqCst);
+        }
+        info.players[0].stats.modify(|stats| stats.kos = 2);
+        info.players[1].stats.modify(|stats| stats.kos = 1);
+        info.players[2].stats.modify(|stats| stats.kos = 2);
+        info.players[2].stats.modify(|stats| stats.falls = 1);
+        start(&info);
+        update(&info, 60, true, MatchMode::Time);
+        update(&info, 0, true, MatchMode::Time);
+        assert!(tied_ports(&info, MatchMode::Time).is_empty());
+        assert!(!info.clock().sudden_death);
+
+        // a KO for port 1 ties it with port 0, while port 2 is a point behind
+        info.players[1].stats.modify(|stats| stats.kos = 2);
+        assert_eq!(tied_ports(&info, MatchMode::Time), [0, 1]);
+        update(&info, 0, true, MatchMode::Time);
+        assert!(info.clock().sudden_death);
+    }
+
+    #[test]
+    fn test_team_sudden_death() {
+        // team 0 has three stocks between its players, as does team 1's last player
+        let info = Info::new();
+        for (player, (team, stocks)) in info.players.iter().zip([(0, 2), (0, 1), (1, 0), (1, 3)]) {
+            player.is_in_game.store(true, Ordering::SeqCst);
+            player.team.store(Some(team));
+            player.stocks.store(stocks, Ordering::SeqCst);
+        }
+        assert_eq!(tied_ports(&info, MatchMode::Stock), [0, 1, 3]);
+    }
+}
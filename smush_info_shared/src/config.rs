@@ -0,0 +1,244 @@
+//! The plugin's settings file, read once at boot from [`CONFIG_PATH`] on the SD card.
+//!
+//! Every key is optional and falls back to the value the plugin has always used, so an empty file
+//! (or no file) behaves exactly like a build without one.
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use serde::{Serialize, Deserialize};
+
+use crate::protocol::MIN_INTERVAL_MS;
+
+pub const CONFIG_PATH: &str = "sd:/ultimate/smush_info/config.toml";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub transports: Transports,
+    pub hooks: Hooks,
+    pub offsets: Offsets,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub listen_address: Ipv4Addr,
+    /// Newline-delimited JSON port.
+    pub port: u16,
+    pub websocket_port: u16,
+    pub http_port: u16,
+    /// Push interval for clients that don't ask for their own.
+    pub interval_ms: u64,
+    /// Push every this many game frames by default instead of every `interval_ms`; `0` keeps
+    /// the interval.
+    pub every_n_frames: u32,
+    pub max_clients: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_address: Ipv4Addr::UNSPECIFIED,
+            port: 4242,
+            websocket_port: 4243,
+            http_port: 4244,
+            interval_ms: 160,
+            every_n_frames: 0,
+            max_clients: 8,
+        }
+    }
+}
+
+/// Which servers the plugin starts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Transports {
+    pub tcp: bool,
+    pub websocket: bool,
+    pub http: bool,
+    pub discovery: bool,
+}
+
+impl Default for Transports {
+    fn default() -> Self {
+        Self { tcp: true, websocket: true, http: true, discovery: true }
+    }
+}
+
+/// Groups of game hooks that can be turned off, e.g. when one crashes on a new game version.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+    pub arena_id: bool,
+    pub close_arena: bool,
+    pub player_tags: bool,
+    pub css_fighter_selected: bool,
+    pub stage_selected: bool,
+    pub hero_menu: bool,
+    pub fighter_status: bool,
+    pub per_frame: bool,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            arena_id: true,
+            close_arena: true,
+            player_tags: true,
+            css_fighter_selected: true,
+            stage_selected: true,
+            hero_menu: true,
+            fighter_status: true,
+            per_frame: true,
+        }
+    }
+}
+
+/// Offsets into the game's `.text`/`.data` to use instead of searching for or hardcoding them.
+/// Unset ones keep the plugin's own.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Offsets {
+    pub arena_id_strlen: Option<usize>,
+    pub arena_id_caller: Option<usize>,
+    pub close_arena: Option<usize>,
+    pub css_fighter_selected: Option<usize>,
+    pub stage_selected: Option<usize>,
+    pub update_tag_for_player: Option<usize>,
+    pub player_save: Option<usize>,
+    pub player_tag: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    Io(String),
+    Parse(String),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "couldn't read {}: {}", CONFIG_PATH, err),
+            ConfigError::Parse(err) => write!(f, "{} isn't valid: {}", CONFIG_PATH, err),
+            ConfigError::Invalid(err) => write!(f, "{}: {}", CONFIG_PATH, err),
+        }
+    }
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let config: Config = toml::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads and validates the file at `path`. A missing file is not an error.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(ConfigError::Io(err.to_string())),
+        }
+    }
+
+    /// Like [`load`](Self::load), but falls back to the defaults and returns the error alongside
+    /// them so the caller can log it.
+    pub fn load_or_default(path: impl AsRef<Path>) -> (Self, Option<ConfigError>) {
+        match Self::load(path) {
+            Ok(config) => (config, None),
+            Err(err) => (Self::default(), Some(err)),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let server = &self.server;
+        let invalid = |msg: String| Err(ConfigError::Invalid(msg));
+
+        let ports = [
+            ("port", server.port, self.transports.tcp),
+            ("websocket_port", server.websocket_port, self.transports.websocket),
+            ("http_port", server.http_port, self.transports.http),
+        ];
+        for (i, &(name, port, enabled)) in ports.iter().enumerate() {
+            if !enabled {
+                continue
+            }
+            if port == 0 {
+                return invalid(format!("server.{} can't be 0", name));
+            }
+            if port == crate::discovery::DISCOVERY_PORT && self.transports.discovery {
+                return invalid(format!("server.{} is the discovery port", name));
+            }
+            if let Some(&(other, ..)) = ports[..i].iter().find(|(_, other, enabled)| *enabled && *other == port) {
+                return invalid(format!("server.{} and server.{} are both {}", other, name, port));
+            }
+        }
+
+        if server.interval_ms < MIN_INTERVAL_MS {
+            return invalid(format!("server.interval_ms must be at least {}", MIN_INTERVAL_MS));
+        }
+        if server.max_clients == 0 {
+            return invalid("server.max_clients must be at least 1".to_owned());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_is_default() {
+        assert_eq!(Config::parse(""), Ok(Config::default()));
+    }
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse(r#"
+            [server]
+            listen_address = "192.168.1.20"
+            port = 5000
+            interval_ms = 50
+
+            [transports]
+            http = false
+
+            [hooks]
+            hero_menu = false
+
+            [offsets]
+            player_tag = 0x52c5758
+        "#).unwrap();
+
+        assert_eq!(config.server.listen_address, Ipv4Addr::new(192, 168, 1, 20));
+        assert_eq!(config.server.port, 5000);
+        assert_eq!(config.server.websocket_port, 4243);
+        assert_eq!(config.server.interval_ms, 50);
+        assert!(!config.transports.http && config.transports.tcp);
+        assert!(!config.hooks.hero_menu && config.hooks.arena_id);
+        assert_eq!(config.offsets.player_tag, Some(0x52c5758));
+        assert_eq!(config.offsets.player_save, None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(Config::parse("[server]\nport = \"4242\""), Err(ConfigError::Parse(_))));
+        assert!(matches!(Config::parse("[sever]\nport = 4242"), Err(ConfigError::Parse(_))));
+        assert_eq!(
+            Config::parse("[server]\nhttp_port = 4242"),
+            Err(ConfigError::Invalid("server.port and server.http_port are both 4242".to_owned()))
+        );
+        assert!(Config::parse("[server]\nhttp_port = 4242\n[transports]\nhttp = false").is_ok());
+        assert!(matches!(Config::parse("[server]\ninterval_ms = 1"), Err(ConfigError::Invalid(_))));
+        assert!(matches!(Config::parse("[server]\nmax_clients = 0"), Err(ConfigError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_missing_file() {
+        assert_eq!(Config::load("/nonexistent/smush_info.toml"), Ok(Config::default()));
+    }
+}
@@ -0,0 +1,346 @@
+//! Diff mode for the info stream: a full snapshot first, then only the fields that changed.
+//!
+//! Every message carries a sequence number so a client can tell it missed one and ask for a
+//! fresh baseline instead of silently drifting.
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize, Deserializer};
+use serde::de;
+
+use crate::{InfoSnapshot, PlayerSnapshot, Character, Stage};
+use crate::clock::Clock;
+use crate::results::MatchEnd;
+use crate::ruleset::Ruleset;
+use crate::stats::Stats;
+use crate::timeline::StockLoss;
+
+// lets a present `null` mean `Some(None)` rather than "unchanged"
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where D: Deserializer<'de>,
+      T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// JSON object keys are strings, and inside an untagged enum like `ServerMessage` serde won't turn
+// them back into numbers by itself
+fn port_keys<'de, D>(deserializer: D) -> Result<BTreeMap<usize, PlayerPatch>, D::Error>
+where D: Deserializer<'de>,
+{
+    #[derive(Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+    #[serde(untagged)]
+    enum Port {
+        Number(usize),
+        Text(String),
+    }
+
+    BTreeMap::<Port, PlayerPatch>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(port, patch)| match port {
+            Port::Number(port) => Ok((port, patch)),
+            Port::Text(port) => port.parse().map(|port| (port, patch)).map_err(de::Error::custom),
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct InfoPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "double_option")]
+    pub arena_id: Option<Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remaining_frames: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_menu: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_match: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_results_screen: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage: Option<Stage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub match_end: Option<MatchEnd>,
+    /// The whole list whenever it changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stock_losses: Option<Vec<StockLoss>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub friendly_fire: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<Clock>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "double_option")]
+    pub ruleset: Option<Option<Ruleset>>,
+    /// Changed players, keyed by port index.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty", deserialize_with = "port_keys")]
+    pub players: BTreeMap<usize, PlayerPatch>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct PlayerPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_in_game: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "double_option")]
+    pub name: Option<Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub character: Option<Character>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stocks: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_destructs: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub damage: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_cpu: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skin: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hero_menu_open: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hero_menu_selected: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hero_menu_selection: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placement: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<Stats>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "double_option")]
+    pub team: Option<Option<u32>>,
+}
+
+/// A message in diff mode.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Delta {
+    Full { seq: u64, info: Box<InfoSnapshot> },
+    Patch { seq: u64, changes: InfoPatch },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeltaError {
+    /// A patch arrived before any full snapshot.
+    NoBaseline,
+    /// A patch was skipped; the client needs a new full snapshot.
+    MissedPatch { expected: u64, got: u64 },
+}
+
+macro_rules! diff_fields {
+    ($prev:ident, $next:ident, $patch:ident, $($field:ident),*) => {
+        $(
+            if $prev.$field != $next.$field {
+                $patch.$field = Some($next.$field.clone());
+            }
+        )*
+    };
+}
+
+macro_rules! apply_fields {
+    ($target:ident, $patch:ident, $($field:ident),*) => {
+        $(
+            if let Some(value) = &$patch.$field {
+                $target.$field = value.clone();
+            }
+        )*
+    };
+}
+
+impl InfoPatch {
+    pub fn is_empty(&self) -> bool {
+        *self == InfoPatch::default()
+    }
+}
+
+impl PlayerPatch {
+    pub fn is_empty(&self) -> bool {
+        *self == PlayerPatch::default()
+    }
+}
+
+/// Returns the fields of `next` that differ from `prev`.
+pub fn diff(prev: &InfoSnapshot, next: &InfoSnapshot) -> InfoPatch {
+    let mut patch = InfoPatch::default();
+    diff_fields!(prev, next, patch, arena_id, remaining_frames, current_menu, is_match, is_results_screen, stage, frame, match_end, stock_losses, friendly_fire, clock, ruleset);
+
+    for (i, (prev, next)) in prev.players.iter().zip(next.players.iter()).enumerate() {
+        let player = diff_player(prev, next);
+        if !player.is_empty() {
+            patch.players.insert(i, player);
+        }
+    }
+
+    patch
+}
+
+fn diff_player(prev: &PlayerSnapshot, next: &PlayerSnapshot) -> PlayerPatch {
+    let mut patch = PlayerPatch::default();
+    diff_fields!(
+        prev, next, patch,
+        is_in_game, name, character, stocks, self_destructs, damage, is_cpu, skin, x, y,
+        hero_menu_open, hero_menu_selected, hero_menu_selection, placement, stats, team
+    );
+    patch
+}
+
+/// Writes every field present in `patch` into `info`. Unknown ports are ignored.
+pub fn apply(info: &mut InfoSnapshot, patch: &InfoPatch) {
+    apply_fields!(info, patch, arena_id, remaining_frames, current_menu, is_match, is_results_screen, stage, frame, match_end, stock_losses, friendly_fire, clock, ruleset);
+
+    for (&i, player_patch) in &patch.players {
+        if let Some(player) = info.players.get_mut(i) {
+            apply_fields!(
+                player, player_patch,
+                is_in_game, name, character, stocks, self_destructs, damage, is_cpu, skin, x, y,
+                hero_menu_open, hero_menu_selected, hero_menu_selection, placement, stats, team
+            );
+        }
+    }
+}
+
+/// Server side: turns successive snapshots into [`Delta`]s for one client.
+#[derive(Default)]
+pub struct DeltaEncoder {
+    prev: Option<InfoSnapshot>,
+    seq: u64,
+}
+
+impl DeltaEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the next message a full snapshot again.
+    pub fn reset(&mut self) {
+        self.prev = None;
+    }
+
+    /// Returns the message to send for `next`, or `None` if nothing changed.
+    pub fn encode(&mut self, next: &InfoSnapshot) -> Option<Delta> {
+        let delta = match &self.prev {
+            None => Delta::Full { seq: self.seq, info: Box::new(next.clone()) },
+            Some(prev) => {
+                let changes = diff(prev, next);
+                if changes.is_empty() {
+                    return None;
+                }
+                Delta::Patch { seq: self.seq, changes }
+            }
+        };
+
+        self.prev = Some(next.clone());
+        self.seq += 1;
+        Some(delta)
+    }
+}
+
+/// Client side: rebuilds the server's state from [`Delta`]s.
+#[derive(Default)]
+pub struct DeltaDecoder {
+    state: Option<InfoSnapshot>,
+    next_seq: u64,
+}
+
+impl DeltaDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> Option<&InfoSnapshot> {
+        self.state.as_ref()
+    }
+
+    pub fn apply(&mut self, delta: Delta) -> Result<&InfoSnapshot, DeltaError> {
+        match delta {
+            Delta::Full { seq, info } => {
+                self.state = Some(*info);
+                self.next_seq = seq + 1;
+            }
+            Delta::Patch { seq, changes } => {
+                let state = self.state.as_mut().ok_or(DeltaError::NoBaseline)?;
+                if seq != self.next_seq {
+                    let expected = self.next_seq;
+                    self.state = None;
+                    return Err(DeltaError::MissedPatch { expected, got: seq });
+                }
+                apply(state, &changes);
+                self.next_seq = seq + 1;
+            }
+        }
+
+        Ok(self.state.as_ref().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod delta_tests {
+    use super::*;
+
+    fn match_snapshot() -> InfoSnapshot {
+        let mut info = InfoSnapshot {
+            is_match: true,
+            stage: Stage::BattleField_S,
+            ..Default::default()
+        };
+        info.players[0].is_in_game = true;
+        info.players[0].character = Character::Brave;
+        info.players[0].name = Some("sharlot".to_owned());
+        info.players[0].stocks = 3;
+        info
+    }
+
+    #[test]
+    fn test_diff_apply_round_trip() {
+        let prev = match_snapshot();
+        let mut next = prev.clone();
+        next.players[0].damage = 37.5;
+        next.players[0].name = None;
+        next.arena_id = Some("ABCDE".to_owned());
+
+        let patch = diff(&prev, &next);
+        assert_eq!(patch.players.len(), 1);
+        assert_eq!(patch.stage, None);
+
+        let json = serde_json::to_string(&patch).unwrap();
+        assert_eq!(json, "{\"arena_id\":\"ABCDE\",\"players\":{\"0\":{\"name\":null,\"damage\":37.5}}}");
+        let patch: InfoPatch = serde_json::from_str(&json).unwrap();
+
+        let mut rebuilt = prev;
+        apply(&mut rebuilt, &patch);
+        assert_eq!(rebuilt, next);
+    }
+
+    #[test]
+    fn test_encoder_decoder() {
+        let mut encoder = DeltaEncoder::new();
+        let mut decoder = DeltaDecoder::new();
+
+        let mut info = match_snapshot();
+        let full = encoder.encode(&info).unwrap();
+        assert!(matches!(full, Delta::Full { seq: 0, .. }));
+        assert_eq!(decoder.apply(full).unwrap(), &info);
+
+        assert_eq!(encoder.encode(&info), None);
+
+        info.players[0].stocks = 2;
+        let patch = encoder.encode(&info).unwrap();
+        assert_eq!(decoder.apply(patch).unwrap(), &info);
+    }
+
+    #[test]
+    fn test_missed_patch() {
+        let mut encoder = DeltaEncoder::new();
+        let mut decoder = DeltaDecoder::new();
+
+        let mut info = match_snapshot();
+        decoder.apply(encoder.encode(&info).unwrap()).unwrap();
+
+        info.players[0].damage = 10.0;
+        let _lost = encoder.encode(&info).unwrap();
+        info.players[0].damage = 20.0;
+        let patch = encoder.encode(&info).unwrap();
+
+        assert_eq!(decoder.apply(patch), Err(DeltaError::MissedPatch { expected: 1, got: 2 }));
+        assert_eq!(decoder.state(), None);
+    }
+}
@@ -0,0 +1,136 @@
+//! Finding Switches running the plugin on the local network.
+//!
+//! A client broadcasts [`PROBE`] to UDP port [`DISCOVERY_PORT`] and every plugin that hears it
+//! answers with an [`Announcement`]. The Switch's address is taken from where the answer came from.
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+use serde::{Serialize, Deserialize};
+
+pub const DISCOVERY_PORT: u16 = 4245;
+
+pub const PROBE: &[u8] = b"smush_info?";
+
+/// What the plugin answers a probe with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Announcement {
+    /// Nickname of the user account last opened on the console, if it could be read. Games can't
+    /// read the console's own name.
+    pub nickname: Option<String>,
+    pub plugin_version: String,
+    /// Newline-delimited JSON port, unless that server is turned off.
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub websocket_port: Option<u16>,
+    #[serde(default)]
+    pub http_port: Option<u16>,
+}
+
+/// A Switch that answered a probe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discovered {
+    pub ip: IpAddr,
+    pub announcement: Announcement,
+}
+
+impl Discovered {
+    /// Address of the newline-delimited JSON server, if it's on.
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.announcement.port.map(|port| SocketAddr::new(self.ip, port))
+    }
+}
+
+pub fn is_probe(packet: &[u8]) -> bool {
+    packet == PROBE
+}
+
+impl Announcement {
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+
+    pub fn decode(packet: &[u8]) -> Option<Self> {
+        serde_json::from_slice(packet).ok()
+    }
+}
+
+/// Broadcasts a probe and collects every answer that arrives within `timeout`, one per Switch.
+pub fn discover(timeout: Duration) -> io::Result<Vec<Discovered>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    socket.send_to(PROBE, (Ipv4Addr::BROADCAST, DISCOVERY_PORT))?;
+    collect_answers(&socket, timeout)
+}
+
+fn collect_answers(socket: &UdpSocket, timeout: Duration) -> io::Result<Vec<Discovered>> {
+    let deadline = Instant::now() + timeout;
+    let mut found = BTreeMap::new();
+    let mut buf = [0u8; 0x400];
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::ZERO {
+            break
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        match socket.recv_from(&mut buf) {
+            Ok((len, from)) => {
+                if let Some(announcement) = Announcement::decode(&buf[..len]) {
+                    found.insert(from.ip(), announcement);
+                }
+            }
+            Err(err) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => break,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(found.into_iter().map(|(ip, announcement)| Discovered { ip, announcement }).collect())
+}
+
+#[cfg(test)]
+mod discovery_tests {
+    use super::*;
+
+    #[test]
+    fn test_announcement_round_trip() {
+        let announcement = Announcement {
+            nickname: Some("sharlot's switch".to_owned()),
+            plugin_version: "0.3.0".to_owned(),
+            port: Some(4242),
+            websocket_port: Some(4243),
+            http_port: None,
+        };
+        assert_eq!(Announcement::decode(&announcement.encode()), Some(announcement));
+        assert_eq!(Announcement::decode(PROBE), None);
+        assert!(is_probe(b"smush_info?"));
+    }
+
+    #[test]
+    fn test_collect_answers() {
+        // stand in for the plugin on a random loopback port, since broadcasts can't be relied on here
+        let plugin = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        client.send_to(PROBE, plugin.local_addr().unwrap()).unwrap();
+
+        let mut buf = [0u8; 0x40];
+        let (len, from) = plugin.recv_from(&mut buf).unwrap();
+        assert!(is_probe(&buf[..len]));
+
+        let announcement = Announcement {
+            nickname: None,
+            plugin_version: "0.3.0".to_owned(),
+            port: Some(4242),
+            websocket_port: None,
+            http_port: None,
+        };
+        plugin.send_to(&announcement.encode(), from).unwrap();
+        plugin.send_to(&announcement.encode(), from).unwrap();
+
+        let found = collect_answers(&client, Duration::from_millis(200)).unwrap();
+        assert_eq!(found, [Discovered { ip: Ipv4Addr::LOCALHOST.into(), announcement }]);
+        assert_eq!(found[0].addr(), Some(SocketAddr::from((Ipv4Addr::LOCALHOST, 4242))));
+    }
+}
@@ -0,0 +1,260 @@
+//! Discrete things that happened, derived by comparing successive snapshots.
+use serde::{Serialize, Deserialize};
+
+use crate::{InfoSnapshot, Character, Stage};
+use crate::results::MatchResult;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    MatchStarted { stage: Stage, ports: Vec<usize> },
+    MatchEnded,
+    StockLost { port: usize, stocks: u32 },
+    /// Every player on a team is out of stocks.
+    TeamEliminated { team: u32 },
+    SelfDestruct { port: usize, self_destructs: u32 },
+    CharacterChanged { port: usize, character: Character, skin: u32 },
+    StageSelected { stage: Stage },
+    ArenaJoined { arena_id: String },
+    ArenaLeft { arena_id: String },
+    HeroMenuOpened { port: usize },
+    HeroSpellChosen { port: usize, selection: u32 },
+    ResultsShown,
+    /// The game's ranking of the match that just ended.
+    MatchResult(MatchResult),
+}
+
+impl Event {
+    /// The `type` tag this event serializes with, e.g. `"stock_lost"`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::MatchStarted { .. } => "match_started",
+            Event::MatchEnded => "match_ended",
+            Event::StockLost { .. } => "stock_lost",
+            Event::TeamEliminated { .. } => "team_eliminated",
+            Event::SelfDestruct { .. } => "self_destruct",
+            Event::CharacterChanged { .. } => "character_changed",
+            Event::StageSelected { .. } => "stage_selected",
+            Event::ArenaJoined { .. } => "arena_joined",
+            Event::ArenaLeft { .. } => "arena_left",
+            Event::HeroMenuOpened { .. } => "hero_menu_opened",
+            Event::HeroSpellChosen { .. } => "hero_spell_chosen",
+            Event::ResultsShown => "results_shown",
+            Event::MatchResult(_) => "match_result",
+        }
+    }
+}
+
+/// Turns a stream of snapshots into [`Event`]s. The first snapshot only sets the baseline.
+#[derive(Default)]
+pub struct EventDeriver {
+    prev: Option<InfoSnapshot>,
+}
+
+impl EventDeriver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, next: &InfoSnapshot) -> Vec<Event> {
+        let events = match &self.prev {
+            Some(prev) => derive(prev, next),
+            None => Vec::new(),
+        };
+        self.prev = Some(next.clone());
+        events
+    }
+}
+
+fn derive(prev: &InfoSnapshot, next: &InfoSnapshot) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    if prev.arena_id != next.arena_id {
+        if let Some(arena_id) = &prev.arena_id {
+            events.push(Event::ArenaLeft { arena_id: arena_id.clone() });
+        }
+        if let Some(arena_id) = &next.arena_id {
+            events.push(Event::ArenaJoined { arena_id: arena_id.clone() });
+        }
+    }
+
+    if prev.stage != next.stage && next.stage != Stage::None {
+        events.push(Event::StageSelected { stage: next.stage });
+    }
+
+    for (port, (prev, next)) in prev.players.iter().zip(next.players.iter()).enumerate() {
+        if (prev.character, prev.skin) != (next.character, next.skin) && next.character != Character::None {
+            events.push(Event::CharacterChanged { port, character: next.character, skin: next.skin });
+        }
+    }
+
+    if !prev.is_match && next.is_match {
+        let ports = next.players.iter()
+            .enumerate()
+            .filter(|(_, player)| player.is_in_game)
+            .map(|(port, _)| port)
+            .collect();
+        events.push(Event::MatchStarted { stage: next.stage, ports });
+    }
+
+    if prev.is_match && next.is_match {
+        for (port, (prev, next)) in prev.players.iter().zip(next.players.iter()).enumerate() {
+            if next.stocks < prev.stocks {
+                events.push(Event::StockLost { port, stocks: next.stocks });
+            }
+            if next.self_destructs > prev.self_destructs {
+                events.push(Event::SelfDestruct { port, self_destructs: next.self_destructs });
+            }
+        }
+
+        let prev_teams = prev.teams();
+        for team in next.teams().iter().filter(|team| team.is_eliminated()) {
+            if prev_teams.iter().any(|prev| prev.team == team.team && !prev.is_eliminated()) {
+                events.push(Event::TeamEliminated { team: team.team });
+            }
+        }
+    }
+
+    for (port, (prev, next)) in prev.players.iter().zip(next.players.iter()).enumerate() {
+        if !prev.hero_menu_open && next.hero_menu_open {
+            events.push(Event::HeroMenuOpened { port });
+        }
+        if !prev.hero_menu_selected && next.hero_menu_selected {
+            events.push(Event::HeroSpellChosen { port, selection: next.hero_menu_selection });
+        }
+    }
+
+    if prev.is_match && !next.is_match {
+        events.push(Event::MatchEnded);
+    }
+
+    if !prev.is_results_screen && next.is_results_screen {
+        events.push(Event::ResultsShown);
+    }
+
+    if prev.match_end != next.match_end {
+        if let Some(result) = next.match_result() {
+            events.push(Event::MatchResult(result));
+        }
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod events_tests {
+    use super::*;
+    use crate::results::{MatchEnd, Placement};
+
+    #[test]
+    fn test_match_flow() {
+        let mut deriver = EventDeriver::new();
+        let mut info = InfoSnapshot::default();
+        assert!(deriver.update(&info).is_empty());
+
+        info.players[0].character = Character::Brave;
+        info.players[1].character = Character::Mario;
+        info.stage = Stage::Battle_DK_Jungle;
+        assert_eq!(deriver.update(&info), [
+            Event::StageSelected { stage: Stage::Battle_DK_Jungle },
+            Event::CharacterChanged { port: 0, character: Character::Brave, skin: 0 },
+            Event::CharacterChanged { port: 1, character: Character::Mario, skin: 0 },
+        ]);
+
+        info.is_match = true;
+        for player in &mut info.players[..2] {
+            player.is_in_game = true;
+            player.stocks = 3;
+        }
+        assert_eq!(deriver.update(&info), [
+            Event::MatchStarted { stage: Stage::Battle_DK_Jungle, ports: vec![0, 1] },
+        ]);
+
+        info.players[1].stocks = 2;
+        info.players[1].self_destructs = 1;
+        info.players[0].hero_menu_open = true;
+        assert_eq!(deriver.update(&info), [
+            Event::StockLost { port: 1, stocks: 2 },
+            Event::SelfDestruct { port: 1, self_destructs: 1 },
+            Event::HeroMenuOpened { port: 0 },
+        ]);
+
+        info.players[0].hero_menu_selected = true;
+        info.players[0].hero_menu_selection = 3;
+        assert_eq!(deriver.update(&info), [Event::HeroSpellChosen { port: 0, selection: 3 }]);
+
+        info.is_match = false;
+        info.is_results_screen = true;
+        assert_eq!(deriver.update(&info), [Event::MatchEnded, Event::ResultsShown]);
+
+        info.match_end = MatchEnd::Stocks;
+        info.players[0].placement = 1;
+        info.players[1].placement = 2;
+        assert_eq!(deriver.update(&info), [Event::MatchResult(info.match_result().unwrap())]);
+    }
+
+    #[test]
+    fn test_team_eliminated() {
+        let mut deriver = EventDeriver::new();
+        let mut info = InfoSnapshot { is_match: true, ..Default::default() };
+        for (player, team) in info.players.iter_mut().zip(&[0, 1, 0, 1]) {
+            player.is_in_game = true;
+            player.team = Some(*team);
+            player.stocks = 1;
+        }
+        deriver.update(&info);
+
+        info.players[1].stocks = 0;
+        assert_eq!(deriver.update(&info), [Event::StockLost { port: 1, stocks: 0 }]);
+
+        info.players[3].stocks = 0;
+        assert_eq!(deriver.update(&info), [
+            Event::StockLost { port: 3, stocks: 0 },
+            Event::TeamEliminated { team: 1 },
+        ]);
+    }
+
+    #[test]
+    fn test_arena() {
+        let mut deriver = EventDeriver::new();
+        let mut info = InfoSnapshot::default();
+        deriver.update(&info);
+
+        info.arena_id = Some("ABCDE".to_owned());
+        assert_eq!(deriver.update(&info), [Event::ArenaJoined { arena_id: "ABCDE".to_owned() }]);
+
+        info.arena_id = Some("FGHJK".to_owned());
+        assert_eq!(deriver.update(&info), [
+            Event::ArenaLeft { arena_id: "ABCDE".to_owned() },
+            Event::ArenaJoined { arena_id: "FGHJK".to_owned() },
+        ]);
+    }
+
+    #[test]
+    fn test_stocks_reset_between_matches() {
+        let mut deriver = EventDeriver::new();
+        let mut info = InfoSnapshot::default();
+        info.players[0].stocks = 1;
+        deriver.update(&info);
+
+        // stocks going down outside of a match (e.g. the next match's setup) isn't a stock loss
+        info.players[0].stocks = 0;
+        assert!(deriver.update(&info).is_empty());
+    }
+
+    #[test]
+    fn test_serde() {
+        let json = serde_json::to_string(&Event::StockLost { port: 1, stocks: 2 }).unwrap();
+        assert_eq!(json, "{\"type\":\"stock_lost\",\"port\":1,\"stocks\":2}");
+        assert_eq!(Event::StockLost { port: 1, stocks: 2 }.name(), "stock_lost");
+
+        let result = Event::MatchResult(MatchResult {
+            placements: vec![Placement { port: 0, placement: 1 }],
+            winners: vec![0],
+            end: MatchEnd::Time,
+        });
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(json, "{\"type\":\"match_result\",\"placements\":[{\"port\":0,\"placement\":1}],\"winners\":[0],\"end\":\"time\"}");
+        assert_eq!(serde_json::from_str::<Event>(&json).unwrap(), result);
+    }
+}
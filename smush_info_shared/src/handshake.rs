@@ -0,0 +1,151 @@
+//! The first messages on a connection. The server opens with a [`Hello`] describing itself, and
+//! the client may answer with a [`ClientHello`] choosing how it wants the stream encoded.
+use std::collections::BTreeMap;
+use serde::{Serialize, Deserialize};
+use serde_json::{Map, Value};
+
+use crate::IdFormat;
+use crate::protocol::Encoding;
+
+/// Bumped whenever a field is removed or changes meaning. Adding fields does not bump it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Hello {
+    pub plugin_version: String,
+    pub schema_version: u32,
+    /// Display version of the game, e.g. `"13.0.2"`, if the plugin could read it.
+    pub game_version: Option<String>,
+    /// Whether each hook found its target in this game version.
+    pub hooks: BTreeMap<String, bool>,
+}
+
+/// Everything is optional; anything left out keeps the server's default.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ClientHello {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_format: Option<IdFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_ms: Option<u64>,
+    /// Push every this many game frames instead of every `interval_ms`; `0` uses the interval.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every_n_frames: Option<u32>,
+    /// `Info` fields to send, e.g. `["is_match", "players.damage"]`. Everything when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Vec<String>>,
+    /// Takes effect right after the server's welcome, which is still sent the old way.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<Encoding>,
+}
+
+/// Trims info messages down to the fields a client asked for.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FieldFilter {
+    info: Vec<String>,
+    player: Vec<String>,
+}
+
+impl FieldFilter {
+    /// Top-level names select `Info` fields; `players.<name>` selects a field of each player.
+    pub fn new(fields: &[String]) -> Self {
+        let mut filter = Self::default();
+        for field in fields {
+            match field.strip_prefix("players.") {
+                Some(player_field) => {
+                    filter.player.push(player_field.to_owned());
+                    if !filter.info.iter().any(|f| f == "players") {
+                        filter.info.push("players".to_owned());
+                    }
+                }
+                None => filter.info.push(field.clone()),
+            }
+        }
+        filter
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.info.is_empty()
+    }
+
+    /// Filters a serialized snapshot or [`Delta`](crate::delta::Delta) in place. Other messages are
+    /// left alone.
+    pub fn apply(&self, msg: &mut Value) {
+        if self.is_empty() {
+            return
+        }
+
+        let info = if msg.get("full").is_some() {
+            msg.pointer_mut("/full/info")
+        } else if msg.get("patch").is_some() {
+            msg.pointer_mut("/patch/changes")
+        } else if msg.get("players").is_some() {
+            Some(msg)
+        } else {
+            None
+        };
+
+        if let Some(Value::Object(info)) = info {
+            self.filter_info(info);
+        }
+    }
+
+    fn filter_info(&self, info: &mut Map<String, Value>) {
+        info.retain(|key, _| self.info.iter().any(|f| f == key));
+        if self.player.is_empty() {
+            return
+        }
+
+        let players: Box<dyn Iterator<Item = &mut Value>> = match info.get_mut("players") {
+            Some(Value::Array(players)) => Box::new(players.iter_mut()),
+            Some(Value::Object(players)) => Box::new(players.values_mut()),
+            _ => return,
+        };
+        for player in players {
+            if let Value::Object(player) = player {
+                player.retain(|key, _| self.player.iter().any(|f| f == key));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod handshake_tests {
+    use super::*;
+    use crate::InfoSnapshot;
+    use crate::delta::DeltaEncoder;
+
+    #[test]
+    fn test_client_hello_defaults() {
+        let hello: ClientHello = serde_json::from_str("{\"id_format\":\"slug\"}").unwrap();
+        assert_eq!(hello, ClientHello { id_format: Some(IdFormat::Slug), ..Default::default() });
+    }
+
+    #[test]
+    fn test_filter_snapshot() {
+        let filter = FieldFilter::new(&["is_match".to_owned(), "players.damage".to_owned()]);
+        let mut msg = serde_json::to_value(InfoSnapshot::default()).unwrap();
+        filter.apply(&mut msg);
+
+        let info = msg.as_object().unwrap();
+        assert_eq!(info.keys().collect::<Vec<_>>(), ["is_match", "players"]);
+        assert_eq!(msg["players"][0], serde_json::json!({ "damage": 0.0 }));
+    }
+
+    #[test]
+    fn test_filter_delta() {
+        let filter = FieldFilter::new(&["players.stocks".to_owned()]);
+        let mut encoder = DeltaEncoder::new();
+        let mut info = InfoSnapshot::default();
+        encoder.encode(&info);
+
+        info.is_match = true;
+        info.players[2].stocks = 3;
+        info.players[2].damage = 12.0;
+        let mut msg = serde_json::to_value(encoder.encode(&info).unwrap()).unwrap();
+        filter.apply(&mut msg);
+
+        assert_eq!(msg["patch"]["changes"], serde_json::json!({ "players": { "2": { "stocks": 3 } } }));
+    }
+}
@@ -0,0 +1,238 @@
+//! Just enough HTTP/1.1 to read a request head off a socket, plus the routes and responses of
+//! the plugin's polling API.
+
+/// Requests with a longer head than this are rejected.
+pub const MAX_HEAD_LEN: usize = 0x2000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HttpError {
+    TooLong,
+    Malformed,
+}
+
+/// Endpoints served on the HTTP port.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Route {
+    /// `GET /info`: the current `Info` as JSON.
+    Info,
+    /// `GET /players/{port}`: a single `Player` as JSON.
+    Player(usize),
+    /// `GET /events`: a Server-Sent Events stream of [`Event`](crate::events::Event)s.
+    Events,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Ok,
+    BadRequest,
+    NotFound,
+    MethodNotAllowed,
+}
+
+impl Status {
+    pub fn code(self) -> u16 {
+        match self {
+            Status::Ok => 200,
+            Status::BadRequest => 400,
+            Status::NotFound => 404,
+            Status::MethodNotAllowed => 405,
+        }
+    }
+
+    pub fn reason(self) -> &'static str {
+        match self {
+            Status::Ok => "OK",
+            Status::BadRequest => "Bad Request",
+            Status::NotFound => "Not Found",
+            Status::MethodNotAllowed => "Method Not Allowed",
+        }
+    }
+}
+
+impl Request {
+    /// Looks up a header, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The path without its query string.
+    pub fn path(&self) -> &str {
+        self.path.split('?').next().unwrap_or_default()
+    }
+
+    /// Looks up a query parameter, e.g. `ids` in `/info?ids=slug`. No percent-decoding is done.
+    pub fn query(&self, name: &str) -> Option<&str> {
+        let (_, query) = self.path.split_once('?')?;
+        query.split('&')
+            .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Works out which endpoint a request is for. `players` is how many ports exist.
+    pub fn route(&self, players: usize) -> Result<Route, Status> {
+        let route = match self.path().trim_end_matches('/') {
+            "/info" => Route::Info,
+            "/events" => Route::Events,
+            path => {
+                let port = path.strip_prefix("/players/").ok_or(Status::NotFound)?;
+                match port.parse() {
+                    Ok(port) if port < players => Route::Player(port),
+                    Ok(_) => return Err(Status::NotFound),
+                    Err(_) => return Err(Status::BadRequest),
+                }
+            }
+        };
+
+        if self.method != "GET" {
+            return Err(Status::MethodNotAllowed);
+        }
+        Ok(route)
+    }
+
+    /// Whether a comma-separated header such as `Connection` contains `token`.
+    pub fn header_has_token(&self, name: &str, token: &str) -> bool {
+        self.header(name)
+            .is_some_and(|value| value.split(',').any(|part| part.trim().eq_ignore_ascii_case(token)))
+    }
+}
+
+/// Parses a request head from the start of `buf`. Returns `Ok(None)` until the blank line ending
+/// the head has arrived, otherwise the request and how many bytes it took up.
+pub fn parse_request(buf: &[u8]) -> Result<Option<(Request, usize)>, HttpError> {
+    let end = match buf.windows(4).position(|window| window == b"\r\n\r\n") {
+        Some(end) => end,
+        None if buf.len() > MAX_HEAD_LEN => return Err(HttpError::TooLong),
+        None => return Ok(None),
+    };
+
+    let head = std::str::from_utf8(&buf[..end]).map_err(|_| HttpError::Malformed)?;
+    let mut lines = head.split("\r\n");
+
+    let mut request_line = lines.next().ok_or(HttpError::Malformed)?.split(' ');
+    let method = request_line.next().ok_or(HttpError::Malformed)?;
+    let path = request_line.next().ok_or(HttpError::Malformed)?;
+    if !request_line.next().is_some_and(|version| version.starts_with("HTTP/1.")) {
+        return Err(HttpError::Malformed);
+    }
+
+    let headers = lines
+        .map(|line| {
+            let (key, value) = line.split_once(':').ok_or(HttpError::Malformed)?;
+            Ok((key.trim().to_owned(), value.trim().to_owned()))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let request = Request {
+        method: method.to_owned(),
+        path: path.to_owned(),
+        headers,
+    };
+
+    Ok(Some((request, end + 4)))
+}
+
+/// A complete response that closes the connection once `body` is sent.
+pub fn response(status: Status, content_type: &str, body: &[u8]) -> Vec<u8> {
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\n\
+         Content-Type: {}\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Connection: close\r\n\r\n",
+        status.code(), status.reason(), content_type, body.len()
+    ).into_bytes();
+    response.extend_from_slice(body);
+    response
+}
+
+/// A plain-text response carrying just the status reason.
+pub fn error_response(status: Status) -> Vec<u8> {
+    response(status, "text/plain", status.reason().as_bytes())
+}
+
+/// Response head opening a Server-Sent Events stream; [`sse_event`]s follow it.
+pub fn event_stream_head() -> Vec<u8> {
+    b"HTTP/1.1 200 OK\r\n\
+      Content-Type: text/event-stream\r\n\
+      Cache-Control: no-cache\r\n\
+      Access-Control-Allow-Origin: *\r\n\
+      Connection: keep-alive\r\n\r\n".to_vec()
+}
+
+/// One Server-Sent Event. `data` is a single line of JSON; a trailing newline is dropped.
+pub fn sse_event(name: &str, data: &[u8]) -> Vec<u8> {
+    let data = data.strip_suffix(b"\n").unwrap_or(data);
+    let mut event = format!("event: {}\ndata: ", name).into_bytes();
+    event.extend_from_slice(data);
+    event.extend_from_slice(b"\n\n");
+    event
+}
+
+#[cfg(test)]
+mod http_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let raw = b"GET /info HTTP/1.1\r\nHost: switch:4243\r\nConnection: keep-alive, Upgrade\r\n\r\nrest";
+        assert_eq!(parse_request(&raw[..20]), Ok(None));
+
+        let (request, len) = parse_request(raw).unwrap().unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/info");
+        assert_eq!(request.header("host"), Some("switch:4243"));
+        assert!(request.header_has_token("connection", "upgrade"));
+        assert_eq!(&raw[len..], b"rest");
+    }
+
+    fn request(method: &str, path: &str) -> Request {
+        Request { method: method.to_owned(), path: path.to_owned(), headers: Vec::new() }
+    }
+
+    #[test]
+    fn test_route() {
+        assert_eq!(request("GET", "/info").route(8), Ok(Route::Info));
+        assert_eq!(request("GET", "/info/?ids=slug").route(8), Ok(Route::Info));
+        assert_eq!(request("GET", "/players/3").route(8), Ok(Route::Player(3)));
+        assert_eq!(request("GET", "/events").route(8), Ok(Route::Events));
+        assert_eq!(request("GET", "/players/8").route(8), Err(Status::NotFound));
+        assert_eq!(request("GET", "/players/p1").route(8), Err(Status::BadRequest));
+        assert_eq!(request("GET", "/").route(8), Err(Status::NotFound));
+        assert_eq!(request("POST", "/info").route(8), Err(Status::MethodNotAllowed));
+    }
+
+    #[test]
+    fn test_query() {
+        let request = request("GET", "/info?pretty&ids=slug");
+        assert_eq!(request.path(), "/info");
+        assert_eq!(request.query("ids"), Some("slug"));
+        assert_eq!(request.query("pretty"), Some(""));
+        assert_eq!(request.query("fields"), None);
+    }
+
+    #[test]
+    fn test_responses() {
+        let ok = String::from_utf8(response(Status::Ok, "application/json", b"{}")).unwrap();
+        assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(ok.ends_with("Content-Length: 2\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}"));
+
+        assert_eq!(sse_event("match_ended", b"{\"type\":\"match_ended\"}\n"),
+                   b"event: match_ended\ndata: {\"type\":\"match_ended\"}\n\n");
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert_eq!(parse_request(b"hello\r\n\r\n"), Err(HttpError::Malformed));
+        assert_eq!(parse_request(&[b'a'; MAX_HEAD_LEN + 1]), Err(HttpError::TooLong));
+    }
+}
@@ -0,0 +1,1147 @@
+//! Stable names for [`Character`] and [`Stage`].
+//!
+//! The numeric ids are just enum discriminants, so adding a fighter or stage shifts every later
+//! value. Slugs are the game's own internal names (`FIGHTER_KIND_BRAVE` is `"brave"`) and never
+//! move. Serialization uses numbers unless wrapped in [`with_id_format`]; deserialization accepts
+//! either.
+use core::cell::Cell;
+use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::{self, Visitor};
+
+use crate::{Character, Stage};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum IdFormat {
+    #[default]
+    Number,
+    Slug,
+}
+
+thread_local! {
+    static ID_FORMAT: Cell<IdFormat> = const { Cell::new(IdFormat::Number) };
+}
+
+/// Runs `f` with `Character`/`Stage` serializing as `format` on the current thread.
+pub fn with_id_format<R>(format: IdFormat, f: impl FnOnce() -> R) -> R {
+    let prev = ID_FORMAT.with(|cell| cell.replace(format));
+    let ret = f();
+    ID_FORMAT.with(|cell| cell.set(prev));
+    ret
+}
+
+fn id_format() -> IdFormat {
+    ID_FORMAT.with(|cell| cell.get())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnknownId {
+    Number(u32),
+    Slug(String),
+}
+
+impl fmt::Display for UnknownId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnknownId::Number(n) => write!(f, "unknown id {}", n),
+            UnknownId::Slug(s) => write!(f, "unknown id \"{}\"", s),
+        }
+    }
+}
+
+impl std::error::Error for UnknownId {}
+
+impl Character {
+    /// Every valid value, indexed by discriminant.
+    pub const ALL: [Character; 98] = [
+        Character::None,
+        Character::Bayonetta,
+        Character::Brave,
+        Character::Buddy,
+        Character::Captain,
+        Character::Chrom,
+        Character::Cloud,
+        Character::Daisy,
+        Character::Dedede,
+        Character::Demon,
+        Character::Diddy,
+        Character::Dolly,
+        Character::Donkey,
+        Character::Duckhunt,
+        Character::Edge,
+        Character::Eflame,
+        Character::Elight,
+        Character::Falco,
+        Character::Fox,
+        Character::Fushigisou,
+        Character::Gamewatch,
+        Character::Ganon,
+        Character::Gaogaen,
+        Character::Gekkouga,
+        Character::Ike,
+        Character::Inkling,
+        Character::Jack,
+        Character::Kamui,
+        Character::Ken,
+        Character::Kirby,
+        Character::Koopa,
+        Character::Koopag,
+        Character::Koopajr,
+        Character::Krool,
+        Character::Link,
+        Character::Littlemac,
+        Character::Lizardon,
+        Character::Lucario,
+        Character::Lucas,
+        Character::Lucina,
+        Character::Luigi,
+        Character::Mario,
+        Character::Mariod,
+        Character::Marth,
+        Character::Master,
+        Character::Metaknight,
+        Character::Mewtwo,
+        Character::Miienemyf,
+        Character::Miienemyg,
+        Character::Miienemys,
+        Character::Miifighter,
+        Character::Miigunner,
+        Character::Miiswordsman,
+        Character::Murabito,
+        Character::Nana,
+        Character::Ness,
+        Character::Packun,
+        Character::Pacman,
+        Character::Palutena,
+        Character::Peach,
+        Character::Pfushigisou,
+        Character::Pichu,
+        Character::Pickel,
+        Character::Pikachu,
+        Character::Pikmin,
+        Character::Pit,
+        Character::Pitb,
+        Character::Plizardon,
+        Character::Popo,
+        Character::Purin,
+        Character::Pzenigame,
+        Character::Reflet,
+        Character::Richter,
+        Character::Ridley,
+        Character::Robot,
+        Character::Rockman,
+        Character::Rosetta,
+        Character::Roy,
+        Character::Ryu,
+        Character::Samus,
+        Character::Samusd,
+        Character::Sheik,
+        Character::Shizue,
+        Character::Shulk,
+        Character::Simon,
+        Character::Snake,
+        Character::Sonic,
+        Character::Szerosuit,
+        Character::Tantan,
+        Character::Toonlink,
+        Character::Trail,
+        Character::Wario,
+        Character::Wiifit,
+        Character::Wolf,
+        Character::Yoshi,
+        Character::Younglink,
+        Character::Zelda,
+        Character::Zenigame,
+    ];
+
+    pub fn slug(self) -> &'static str {
+        use Character::*;
+        match self {
+            None => "none",
+            Bayonetta => "bayonetta",
+            Brave => "brave",
+            Buddy => "buddy",
+            Captain => "captain",
+            Chrom => "chrom",
+            Cloud => "cloud",
+            Daisy => "daisy",
+            Dedede => "dedede",
+            Demon => "demon",
+            Diddy => "diddy",
+            Dolly => "dolly",
+            Donkey => "donkey",
+            Duckhunt => "duckhunt",
+            Edge => "edge",
+            Eflame => "eflame",
+            Elight => "elight",
+            Falco => "falco",
+            Fox => "fox",
+            Fushigisou => "fushigisou",
+            Gamewatch => "gamewatch",
+            Ganon => "ganon",
+            Gaogaen => "gaogaen",
+            Gekkouga => "gekkouga",
+            Ike => "ike",
+            Inkling => "inkling",
+            Jack => "jack",
+            Kamui => "kamui",
+            Ken => "ken",
+            Kirby => "kirby",
+            Koopa => "koopa",
+            Koopag => "koopag",
+            Koopajr => "koopajr",
+            Krool => "krool",
+            Link => "link",
+            Littlemac => "littlemac",
+            Lizardon => "lizardon",
+            Lucario => "lucario",
+            Lucas => "lucas",
+            Lucina => "lucina",
+            Luigi => "luigi",
+            Mario => "mario",
+            Mariod => "mariod",
+            Marth => "marth",
+            Master => "master",
+            Metaknight => "metaknight",
+            Mewtwo => "mewtwo",
+            Miienemyf => "miienemyf",
+            Miienemyg => "miienemyg",
+            Miienemys => "miienemys",
+            Miifighter => "miifighter",
+            Miigunner => "miigunner",
+            Miiswordsman => "miiswordsman",
+            Murabito => "murabito",
+            Nana => "nana",
+            Ness => "ness",
+            Packun => "packun",
+            Pacman => "pacman",
+            Palutena => "palutena",
+            Peach => "peach",
+            Pfushigisou => "pfushigisou",
+            Pichu => "pichu",
+            Pickel => "pickel",
+            Pikachu => "pikachu",
+            Pikmin => "pikmin",
+            Pit => "pit",
+            Pitb => "pitb",
+            Plizardon => "plizardon",
+            Popo => "popo",
+            Purin => "purin",
+            Pzenigame => "pzenigame",
+            Reflet => "reflet",
+            Richter => "richter",
+            Ridley => "ridley",
+            Robot => "robot",
+            Rockman => "rockman",
+            Rosetta => "rosetta",
+            Roy => "roy",
+            Ryu => "ryu",
+            Samus => "samus",
+            Samusd => "samusd",
+            Sheik => "sheik",
+            Shizue => "shizue",
+            Shulk => "shulk",
+            Simon => "simon",
+            Snake => "snake",
+            Sonic => "sonic",
+            Szerosuit => "szerosuit",
+            Tantan => "tantan",
+            Toonlink => "toonlink",
+            Trail => "trail",
+            Wario => "wario",
+            Wiifit => "wiifit",
+            Wolf => "wolf",
+            Yoshi => "yoshi",
+            Younglink => "younglink",
+            Zelda => "zelda",
+            Zenigame => "zenigame",
+            Max => "max",
+        }
+    }
+}
+
+impl TryFrom<u32> for Character {
+    type Error = UnknownId;
+
+    fn try_from(c: u32) -> Result<Self, Self::Error> {
+        Character::ALL.get(c as usize).copied().ok_or(UnknownId::Number(c))
+    }
+}
+
+impl FromStr for Character {
+    type Err = UnknownId;
+
+    fn from_str(c: &str) -> Result<Self, Self::Err> {
+        Character::ALL.iter()
+            .copied()
+            .find(|x| x.slug().eq_ignore_ascii_case(c))
+            .ok_or_else(|| UnknownId::Slug(c.to_owned()))
+    }
+}
+
+impl Serialize for Character {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match id_format() {
+            IdFormat::Number => serializer.serialize_u32(*self as u32),
+            IdFormat::Slug => serializer.serialize_str(self.slug()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Character {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(IdVisitor::<Character>::new())
+    }
+}
+
+impl Stage {
+    /// Every valid value, indexed by discriminant.
+    pub const ALL: [Stage; 365] = [
+        Stage::None,
+        Stage::_75m,
+        Stage::Animal_City,
+        Stage::Animal_Island,
+        Stage::Animal_Village,
+        Stage::BalloonFight,
+        Stage::Battle_75m,
+        Stage::Battle_Animal_City,
+        Stage::Battle_Animal_Island,
+        Stage::Battle_Animal_Village,
+        Stage::Battle_BalloonFight,
+        Stage::Battle_Bayo_Clock,
+        Stage::Battle_Brave_Altar,
+        Stage::Battle_Buddy_Spiral,
+        Stage::Battle_Demon_Dojo,
+        Stage::Battle_DK_Jungle,
+        Stage::Battle_DK_Lodge,
+        Stage::Battle_DK_WaterFall,
+        Stage::Battle_Dolly_Stadium,
+        Stage::Battle_Dracula_Castle,
+        Stage::Battle_DuckHunt,
+        Stage::Battle_End,
+        Stage::Battle_FE_Arena,
+        Stage::Battle_FE_Colloseum,
+        Stage::Battle_FE_Shrine,
+        Stage::Battle_FE_Siege,
+        Stage::Battle_FF_Cave,
+        Stage::Battle_FF_Midgar,
+        Stage::Battle_FlatZoneX,
+        Stage::Battle_Fox_Corneria,
+        Stage::Battle_Fox_LylatCruise,
+        Stage::Battle_Fox_Venom,
+        Stage::Battle_Fzero_Bigblue,
+        Stage::Battle_Fzero_Mutecity3DS,
+        Stage::Battle_Fzero_Porttown,
+        Stage::Battle_Icarus_Angeland,
+        Stage::Battle_Icarus_SkyWorld,
+        Stage::Battle_Icarus_Uprising,
+        Stage::Battle_Ice_Top,
+        Stage::Battle_Jack_Mementoes,
+        Stage::Battle_Kart_CircuitFor,
+        Stage::Battle_Kart_CircuitX,
+        Stage::Battle_Kirby_Cave,
+        Stage::Battle_Kirby_Fountain,
+        Stage::Battle_Kirby_Gameboy,
+        Stage::Battle_Kirby_Greens,
+        Stage::Battle_Kirby_Halberd,
+        Stage::Battle_Kirby_Pupupu64,
+        Stage::Battle_LuigiMansion,
+        Stage::Battle_Mario_3DLand,
+        Stage::Battle_Mario_Castle64,
+        Stage::Battle_Mario_CastleDx,
+        Stage::Battle_Mario_Dolpic,
+        Stage::Battle_Mario_Galaxy,
+        Stage::Battle_Mario_Maker,
+        Stage::Battle_Mario_NewBros2,
+        Stage::Battle_Mario_Odyssey,
+        Stage::Battle_Mario_Paper,
+        Stage::Battle_Mario_Past64,
+        Stage::Battle_Mario_PastUsa,
+        Stage::Battle_Mario_PastX,
+        Stage::Battle_Mario_Rainbow,
+        Stage::Battle_Mario_Uworld,
+        Stage::Battle_MarioBros,
+        Stage::Battle_Metroid_Kraid,
+        Stage::Battle_Metroid_Norfair,
+        Stage::Battle_Metroid_Orpheon,
+        Stage::Battle_Metroid_ZebesDx,
+        Stage::Battle_MG_Shadowmoses,
+        Stage::Battle_Mother_Fourside,
+        Stage::Battle_Mother_Magicant,
+        Stage::Battle_Mother_Newpork,
+        Stage::Battle_Mother_Onett,
+        Stage::Battle_Nintendogs,
+        Stage::Battle_Pac_Land,
+        Stage::Battle_Pickel_World,
+        Stage::Battle_Pictochat2,
+        Stage::Battle_Pikmin_Garden,
+        Stage::Battle_Pikmin_Planet,
+        Stage::Battle_Pilotwings,
+        Stage::Battle_Plankton,
+        Stage::Battle_Poke_Kalos,
+        Stage::Battle_Poke_Stadium,
+        Stage::Battle_Poke_Stadium2,
+        Stage::Battle_Poke_Tengam,
+        Stage::Battle_Poke_Tower,
+        Stage::Battle_Poke_Unova,
+        Stage::Battle_Poke_Yamabuki,
+        Stage::Battle_PunchOutSB,
+        Stage::Battle_PunchOutW,
+        Stage::Battle_Rock_Wily,
+        Stage::Battle_SF_Suzaku,
+        Stage::Battle_Sonic_Greenhill,
+        Stage::Battle_Sonic_Windyhill,
+        Stage::Battle_Spla_Parking,
+        Stage::Battle_StreetPass,
+        Stage::Battle_Tantan_Spring,
+        Stage::Battle_Tomodachi,
+        Stage::Battle_Trail_Castle,
+        Stage::Battle_Wario_Gamer,
+        Stage::Battle_Wario_Madein,
+        Stage::Battle_WiiFit,
+        Stage::Battle_WreckingCrew,
+        Stage::Battle_WufuIsland,
+        Stage::Battle_Xeno_Alst,
+        Stage::Battle_Xeno_Gaur,
+        Stage::Battle_Yoshi_CartBoard,
+        Stage::Battle_Yoshi_Island,
+        Stage::Battle_Yoshi_Story,
+        Stage::Battle_Yoshi_Yoster,
+        Stage::Battle_Zelda_Gerudo,
+        Stage::Battle_Zelda_Greatbay,
+        Stage::Battle_Zelda_Hyrule,
+        Stage::Battle_Zelda_Oldin,
+        Stage::Battle_Zelda_Pirates,
+        Stage::Battle_Zelda_Skyward,
+        Stage::Battle_Zelda_Temple,
+        Stage::Battle_Zelda_Tower,
+        Stage::Battle_Zelda_Train,
+        Stage::BattleField_L,
+        Stage::BattleField_S,
+        Stage::BattleField,
+        Stage::Bayo_Clock,
+        Stage::BonusGame,
+        Stage::BossStage_Dracula,
+        Stage::BossStage_Final1,
+        Stage::BossStage_Final2,
+        Stage::BossStage_Final3,
+        Stage::BossStage_Galleom,
+        Stage::BossStage_GanonBoss,
+        Stage::BossStage_Marx,
+        Stage::BossStage_Rathalos,
+        Stage::Brave_Altar,
+        Stage::Buddy_Spiral,
+        Stage::CampaignMap,
+        Stage::Demon_Dojo,
+        Stage::DK_Jungle,
+        Stage::DK_Lodge,
+        Stage::DK_WaterFall,
+        Stage::Dolly_Stadium,
+        Stage::Dracula_Castle,
+        Stage::DuckHunt,
+        Stage::End_75m,
+        Stage::End_Animal_City,
+        Stage::End_Animal_Island,
+        Stage::End_Animal_Village,
+        Stage::End_BalloonFight,
+        Stage::End_BattleField,
+        Stage::End_Bayo_Clock,
+        Stage::End_Brave_Altar,
+        Stage::End_Buddy_Spiral,
+        Stage::End_Demon_Dojo,
+        Stage::End_DK_Jungle,
+        Stage::End_DK_Lodge,
+        Stage::End_DK_WaterFall,
+        Stage::End_Dolly_Stadium,
+        Stage::End_Dracula_Castle,
+        Stage::End_DuckHunt,
+        Stage::End_FE_Arena,
+        Stage::End_FE_Colloseum,
+        Stage::End_FE_Shrine,
+        Stage::End_FE_Siege,
+        Stage::End_FF_Cave,
+        Stage::End_FF_Midgar,
+        Stage::End_FlatZoneX,
+        Stage::End_Fox_Corneria,
+        Stage::End_Fox_LylatCruise,
+        Stage::End_Fox_Venom,
+        Stage::End_Fzero_Bigblue,
+        Stage::End_Fzero_Mutecity3DS,
+        Stage::End_Fzero_Porttown,
+        Stage::End_Icarus_Angeland,
+        Stage::End_Icarus_SkyWorld,
+        Stage::End_Icarus_Uprising,
+        Stage::End_Ice_Top,
+        Stage::End_Jack_Mementoes,
+        Stage::End_Kart_CircuitFor,
+        Stage::End_Kart_CircuitX,
+        Stage::End_Kirby_Cave,
+        Stage::End_Kirby_Fountain,
+        Stage::End_Kirby_Gameboy,
+        Stage::End_Kirby_Greens,
+        Stage::End_Kirby_Halberd,
+        Stage::End_Kirby_Pupupu64,
+        Stage::End_LuigiMansion,
+        Stage::End_Mario_3DLand,
+        Stage::End_Mario_Castle64,
+        Stage::End_Mario_CastleDx,
+        Stage::End_Mario_Dolpic,
+        Stage::End_Mario_Galaxy,
+        Stage::End_Mario_Maker,
+        Stage::End_Mario_NewBros2,
+        Stage::End_Mario_Odyssey,
+        Stage::End_Mario_Paper,
+        Stage::End_Mario_Past64,
+        Stage::End_Mario_PastUsa,
+        Stage::End_Mario_PastX,
+        Stage::End_Mario_Rainbow,
+        Stage::End_Mario_Uworld,
+        Stage::End_MarioBros,
+        Stage::End_Metroid_Kraid,
+        Stage::End_Metroid_Norfair,
+        Stage::End_Metroid_Orpheon,
+        Stage::End_Metroid_ZebesDx,
+        Stage::End_MG_Shadowmoses,
+        Stage::End_Mother_Fourside,
+        Stage::End_Mother_Magicant,
+        Stage::End_Mother_Newpork,
+        Stage::End_Mother_Onett,
+        Stage::End_Nintendogs,
+        Stage::End_Pac_Land,
+        Stage::End_Pickel_World,
+        Stage::End_Pictochat2,
+        Stage::End_Pikmin_Garden,
+        Stage::End_Pikmin_Planet,
+        Stage::End_Pilotwings,
+        Stage::End_Plankton,
+        Stage::End_Poke_Kalos,
+        Stage::End_Poke_Stadium,
+        Stage::End_Poke_Stadium2,
+        Stage::End_Poke_Tengam,
+        Stage::End_Poke_Tower,
+        Stage::End_Poke_Unova,
+        Stage::End_Poke_Yamabuki,
+        Stage::End_PunchOutSB,
+        Stage::End_PunchOutW,
+        Stage::End_Rock_Wily,
+        Stage::End_SF_Suzaku,
+        Stage::End_Sonic_Greenhill,
+        Stage::End_Sonic_Windyhill,
+        Stage::End_Spla_Parking,
+        Stage::End_StreetPass,
+        Stage::End_Tantan_Spring,
+        Stage::End_Tomodachi,
+        Stage::End_Trail_Castle,
+        Stage::End_Wario_Gamer,
+        Stage::End_Wario_Madein,
+        Stage::End_WiiFit,
+        Stage::End_WreckingCrew,
+        Stage::End_WufuIsland,
+        Stage::End_Xeno_Alst,
+        Stage::End_Xeno_Gaur,
+        Stage::End_Yoshi_CartBoard,
+        Stage::End_Yoshi_Island,
+        Stage::End_Yoshi_Story,
+        Stage::End_Yoshi_Yoster,
+        Stage::End_Zelda_Gerudo,
+        Stage::End_Zelda_Greatbay,
+        Stage::End_Zelda_Hyrule,
+        Stage::End_Zelda_Oldin,
+        Stage::End_Zelda_Pirates,
+        Stage::End_Zelda_Skyward,
+        Stage::End_Zelda_Temple,
+        Stage::End_Zelda_Tower,
+        Stage::End_Zelda_Train,
+        Stage::End,
+        Stage::FE_Arena,
+        Stage::FE_Colloseum,
+        Stage::FE_Shrine,
+        Stage::FE_Siege,
+        Stage::FF_Cave,
+        Stage::FF_Midgar,
+        Stage::FlatZoneX,
+        Stage::Fox_Corneria,
+        Stage::Fox_LylatCruise,
+        Stage::Fox_Venom,
+        Stage::Fzero_Bigblue,
+        Stage::Fzero_Mutecity3DS,
+        Stage::Fzero_Porttown,
+        Stage::HomerunContest,
+        Stage::Icarus_Angeland,
+        Stage::Icarus_SkyWorld,
+        Stage::Icarus_Uprising,
+        Stage::Ice_Top,
+        Stage::Invalid,
+        Stage::Jack_Mementoes,
+        Stage::Kart_CircuitFor,
+        Stage::Kart_CircuitX,
+        Stage::Kirby_Cave,
+        Stage::Kirby_Fountain,
+        Stage::Kirby_Gameboy,
+        Stage::Kirby_Greens,
+        Stage::Kirby_Halberd,
+        Stage::Kirby_Pupupu64,
+        Stage::LuigiMansion,
+        Stage::Mario_3DLand,
+        Stage::Mario_Castle64,
+        Stage::Mario_CastleDx,
+        Stage::Mario_Dolpic,
+        Stage::Mario_Galaxy,
+        Stage::Mario_Maker,
+        Stage::Mario_NewBros2,
+        Stage::Mario_Odyssey,
+        Stage::Mario_Paper,
+        Stage::Mario_Past64,
+        Stage::Mario_PastUsa,
+        Stage::Mario_PastX,
+        Stage::Mario_Rainbow,
+        Stage::Mario_Uworld,
+        Stage::MarioBros,
+        Stage::Metroid_Kraid,
+        Stage::Metroid_Norfair,
+        Stage::Metroid_Orpheon,
+        Stage::Metroid_ZebesDx,
+        Stage::MG_Shadowmoses,
+        Stage::Mother_Fourside,
+        Stage::Mother_Magicant,
+        Stage::Mother_Newpork,
+        Stage::Mother_Onett,
+        Stage::Nintendogs,
+        Stage::Pac_Land,
+        Stage::PhotoStage,
+        Stage::Pickel_World,
+        Stage::Pictochat2,
+        Stage::Pikmin_Garden,
+        Stage::Pikmin_Planet,
+        Stage::Pilotwings,
+        Stage::Plankton,
+        Stage::Poke_Kalos,
+        Stage::Poke_Stadium,
+        Stage::Poke_Stadium2,
+        Stage::Poke_Tengam,
+        Stage::Poke_Tower,
+        Stage::Poke_Unova,
+        Stage::Poke_Yamabuki,
+        Stage::PunchOutSB,
+        Stage::PunchOutW,
+        Stage::ResultStage_Jack,
+        Stage::ResultStage,
+        Stage::Rock_Wily,
+        Stage::SettingStage,
+        Stage::SF_Suzaku,
+        Stage::ShamFight,
+        Stage::Sonic_Greenhill,
+        Stage::Sonic_Windyhill,
+        Stage::SP_Edit,
+        Stage::SpiritsRoulette,
+        Stage::Spla_Parking,
+        Stage::Staffroll,
+        Stage::StreetPass,
+        Stage::Tantan_Spring,
+        Stage::TestStage,
+        Stage::Tomodachi,
+        Stage::Trail_Castle,
+        Stage::Training,
+        Stage::Wario_Gamer,
+        Stage::Wario_Madein,
+        Stage::WiiFit,
+        Stage::WreckingCrew,
+        Stage::WufuIsland,
+        Stage::Xeno_Alst,
+        Stage::Xeno_Gaur,
+        Stage::Yoshi_CartBoard,
+        Stage::Yoshi_Island,
+        Stage::Yoshi_Story,
+        Stage::Yoshi_Yoster,
+        Stage::Zelda_Gerudo,
+        Stage::Zelda_Greatbay,
+        Stage::Zelda_Hyrule,
+        Stage::Zelda_Oldin,
+        Stage::Zelda_Pirates,
+        Stage::Zelda_Skyward,
+        Stage::Zelda_Temple,
+        Stage::Zelda_Tower,
+        Stage::Zelda_Train,
+    ];
+
+    pub fn slug(self) -> &'static str {
+        use Stage::*;
+        match self {
+            None => "none",
+            _75m => "75m",
+            Animal_City => "animal_city",
+            Animal_Island => "animal_island",
+            Animal_Village => "animal_village",
+            BalloonFight => "balloonfight",
+            Battle_75m => "battle_75m",
+            Battle_Animal_City => "battle_animal_city",
+            Battle_Animal_Island => "battle_animal_island",
+            Battle_Animal_Village => "battle_animal_village",
+            Battle_BalloonFight => "battle_balloonfight",
+            Battle_Bayo_Clock => "battle_bayo_clock",
+            Battle_Brave_Altar => "battle_brave_altar",
+            Battle_Buddy_Spiral => "battle_buddy_spiral",
+            Battle_Demon_Dojo => "battle_demon_dojo",
+            Battle_DK_Jungle => "battle_dk_jungle",
+            Battle_DK_Lodge => "battle_dk_lodge",
+            Battle_DK_WaterFall => "battle_dk_waterfall",
+            Battle_Dolly_Stadium => "battle_dolly_stadium",
+            Battle_Dracula_Castle => "battle_dracula_castle",
+            Battle_DuckHunt => "battle_duckhunt",
+            Battle_End => "battle_end",
+            Battle_FE_Arena => "battle_fe_arena",
+            Battle_FE_Colloseum => "battle_fe_colloseum",
+            Battle_FE_Shrine => "battle_fe_shrine",
+            Battle_FE_Siege => "battle_fe_siege",
+            Battle_FF_Cave => "battle_ff_cave",
+            Battle_FF_Midgar => "battle_ff_midgar",
+            Battle_FlatZoneX => "battle_flatzonex",
+            Battle_Fox_Corneria => "battle_fox_corneria",
+            Battle_Fox_LylatCruise => "battle_fox_lylatcruise",
+            Battle_Fox_Venom => "battle_fox_venom",
+            Battle_Fzero_Bigblue => "battle_fzero_bigblue",
+            Battle_Fzero_Mutecity3DS => "battle_fzero_mutecity3ds",
+            Battle_Fzero_Porttown => "battle_fzero_porttown",
+            Battle_Icarus_Angeland => "battle_icarus_angeland",
+            Battle_Icarus_SkyWorld => "battle_icarus_skyworld",
+            Battle_Icarus_Uprising => "battle_icarus_uprising",
+            Battle_Ice_Top => "battle_ice_top",
+            Battle_Jack_Mementoes => "battle_jack_mementoes",
+            Battle_Kart_CircuitFor => "battle_kart_circuitfor",
+            Battle_Kart_CircuitX => "battle_kart_circuitx",
+            Battle_Kirby_Cave => "battle_kirby_cave",
+            Battle_Kirby_Fountain => "battle_kirby_fountain",
+            Battle_Kirby_Gameboy => "battle_kirby_gameboy",
+            Battle_Kirby_Greens => "battle_kirby_greens",
+            Battle_Kirby_Halberd => "battle_kirby_halberd",
+            Battle_Kirby_Pupupu64 => "battle_kirby_pupupu64",
+            Battle_LuigiMansion => "battle_luigimansion",
+            Battle_Mario_3DLand => "battle_mario_3dland",
+            Battle_Mario_Castle64 => "battle_mario_castle64",
+            Battle_Mario_CastleDx => "battle_mario_castledx",
+            Battle_Mario_Dolpic => "battle_mario_dolpic",
+            Battle_Mario_Galaxy => "battle_mario_galaxy",
+            Battle_Mario_Maker => "battle_mario_maker",
+            Battle_Mario_NewBros2 => "battle_mario_newbros2",
+            Battle_Mario_Odyssey => "battle_mario_odyssey",
+            Battle_Mario_Paper => "battle_mario_paper",
+            Battle_Mario_Past64 => "battle_mario_past64",
+            Battle_Mario_PastUsa => "battle_mario_pastusa",
+            Battle_Mario_PastX => "battle_mario_pastx",
+            Battle_Mario_Rainbow => "battle_mario_rainbow",
+            Battle_Mario_Uworld => "battle_mario_uworld",
+            Battle_MarioBros => "battle_mariobros",
+            Battle_Metroid_Kraid => "battle_metroid_kraid",
+            Battle_Metroid_Norfair => "battle_metroid_norfair",
+            Battle_Metroid_Orpheon => "battle_metroid_orpheon",
+            Battle_Metroid_ZebesDx => "battle_metroid_zebesdx",
+            Battle_MG_Shadowmoses => "battle_mg_shadowmoses",
+            Battle_Mother_Fourside => "battle_mother_fourside",
+            Battle_Mother_Magicant => "battle_mother_magicant",
+            Battle_Mother_Newpork => "battle_mother_newpork",
+            Battle_Mother_Onett => "battle_mother_onett",
+            Battle_Nintendogs => "battle_nintendogs",
+            Battle_Pac_Land => "battle_pac_land",
+            Battle_Pickel_World => "battle_pickel_world",
+            Battle_Pictochat2 => "battle_pictochat2",
+            Battle_Pikmin_Garden => "battle_pikmin_garden",
+            Battle_Pikmin_Planet => "battle_pikmin_planet",
+            Battle_Pilotwings => "battle_pilotwings",
+            Battle_Plankton => "battle_plankton",
+            Battle_Poke_Kalos => "battle_poke_kalos",
+            Battle_Poke_Stadium => "battle_poke_stadium",
+            Battle_Poke_Stadium2 => "battle_poke_stadium2",
+            Battle_Poke_Tengam => "battle_poke_tengam",
+            Battle_Poke_Tower => "battle_poke_tower",
+            Battle_Poke_Unova => "battle_poke_unova",
+            Battle_Poke_Yamabuki => "battle_poke_yamabuki",
+            Battle_PunchOutSB => "battle_punchoutsb",
+            Battle_PunchOutW => "battle_punchoutw",
+            Battle_Rock_Wily => "battle_rock_wily",
+            Battle_SF_Suzaku => "battle_sf_suzaku",
+            Battle_Sonic_Greenhill => "battle_sonic_greenhill",
+            Battle_Sonic_Windyhill => "battle_sonic_windyhill",
+            Battle_Spla_Parking => "battle_spla_parking",
+            Battle_StreetPass => "battle_streetpass",
+            Battle_Tantan_Spring => "battle_tantan_spring",
+            Battle_Tomodachi => "battle_tomodachi",
+            Battle_Trail_Castle => "battle_trail_castle",
+            Battle_Wario_Gamer => "battle_wario_gamer",
+            Battle_Wario_Madein => "battle_wario_madein",
+            Battle_WiiFit => "battle_wiifit",
+            Battle_WreckingCrew => "battle_wreckingcrew",
+            Battle_WufuIsland => "battle_wufuisland",
+            Battle_Xeno_Alst => "battle_xeno_alst",
+            Battle_Xeno_Gaur => "battle_xeno_gaur",
+            Battle_Yoshi_CartBoard => "battle_yoshi_cartboard",
+            Battle_Yoshi_Island => "battle_yoshi_island",
+            Battle_Yoshi_Story => "battle_yoshi_story",
+            Battle_Yoshi_Yoster => "battle_yoshi_yoster",
+            Battle_Zelda_Gerudo => "battle_zelda_gerudo",
+            Battle_Zelda_Greatbay => "battle_zelda_greatbay",
+            Battle_Zelda_Hyrule => "battle_zelda_hyrule",
+            Battle_Zelda_Oldin => "battle_zelda_oldin",
+            Battle_Zelda_Pirates => "battle_zelda_pirates",
+            Battle_Zelda_Skyward => "battle_zelda_skyward",
+            Battle_Zelda_Temple => "battle_zelda_temple",
+            Battle_Zelda_Tower => "battle_zelda_tower",
+            Battle_Zelda_Train => "battle_zelda_train",
+            BattleField_L => "battlefield_l",
+            BattleField_S => "battlefield_s",
+            BattleField => "battlefield",
+            Bayo_Clock => "bayo_clock",
+            BonusGame => "bonusgame",
+            BossStage_Dracula => "bossstage_dracula",
+            BossStage_Final1 => "bossstage_final1",
+            BossStage_Final2 => "bossstage_final2",
+            BossStage_Final3 => "bossstage_final3",
+            BossStage_Galleom => "bossstage_galleom",
+            BossStage_GanonBoss => "bossstage_ganonboss",
+            BossStage_Marx => "bossstage_marx",
+            BossStage_Rathalos => "bossstage_rathalos",
+            Brave_Altar => "brave_altar",
+            Buddy_Spiral => "buddy_spiral",
+            CampaignMap => "campaignmap",
+            Demon_Dojo => "demon_dojo",
+            DK_Jungle => "dk_jungle",
+            DK_Lodge => "dk_lodge",
+            DK_WaterFall => "dk_waterfall",
+            Dolly_Stadium => "dolly_stadium",
+            Dracula_Castle => "dracula_castle",
+            DuckHunt => "duckhunt",
+            End_75m => "end_75m",
+            End_Animal_City => "end_animal_city",
+            End_Animal_Island => "end_animal_island",
+            End_Animal_Village => "end_animal_village",
+            End_BalloonFight => "end_balloonfight",
+            End_BattleField => "end_battlefield",
+            End_Bayo_Clock => "end_bayo_clock",
+            End_Brave_Altar => "end_brave_altar",
+            End_Buddy_Spiral => "end_buddy_spiral",
+            End_Demon_Dojo => "end_demon_dojo",
+            End_DK_Jungle => "end_dk_jungle",
+            End_DK_Lodge => "end_dk_lodge",
+            End_DK_WaterFall => "end_dk_waterfall",
+            End_Dolly_Stadium => "end_dolly_stadium",
+            End_Dracula_Castle => "end_dracula_castle",
+            End_DuckHunt => "end_duckhunt",
+            End_FE_Arena => "end_fe_arena",
+            End_FE_Colloseum => "end_fe_colloseum",
+            End_FE_Shrine => "end_fe_shrine",
+            End_FE_Siege => "end_fe_siege",
+            End_FF_Cave => "end_ff_cave",
+            End_FF_Midgar => "end_ff_midgar",
+            End_FlatZoneX => "end_flatzonex",
+            End_Fox_Corneria => "end_fox_corneria",
+            End_Fox_LylatCruise => "end_fox_lylatcruise",
+            End_Fox_Venom => "end_fox_venom",
+            End_Fzero_Bigblue => "end_fzero_bigblue",
+            End_Fzero_Mutecity3DS => "end_fzero_mutecity3ds",
+            End_Fzero_Porttown => "end_fzero_porttown",
+            End_Icarus_Angeland => "end_icarus_angeland",
+            End_Icarus_SkyWorld => "end_icarus_skyworld",
+            End_Icarus_Uprising => "end_icarus_uprising",
+            End_Ice_Top => "end_ice_top",
+            End_Jack_Mementoes => "end_jack_mementoes",
+            End_Kart_CircuitFor => "end_kart_circuitfor",
+            End_Kart_CircuitX => "end_kart_circuitx",
+            End_Kirby_Cave => "end_kirby_cave",
+            End_Kirby_Fountain => "end_kirby_fountain",
+            End_Kirby_Gameboy => "end_kirby_gameboy",
+            End_Kirby_Greens => "end_kirby_greens",
+            End_Kirby_Halberd => "end_kirby_halberd",
+            End_Kirby_Pupupu64 => "end_kirby_pupupu64",
+            End_LuigiMansion => "end_luigimansion",
+            End_Mario_3DLand => "end_mario_3dland",
+            End_Mario_Castle64 => "end_mario_castle64",
+            End_Mario_CastleDx => "end_mario_castledx",
+            End_Mario_Dolpic => "end_mario_dolpic",
+            End_Mario_Galaxy => "end_mario_galaxy",
+            End_Mario_Maker => "end_mario_maker",
+            End_Mario_NewBros2 => "end_mario_newbros2",
+            End_Mario_Odyssey => "end_mario_odyssey",
+            End_Mario_Paper => "end_mario_paper",
+            End_Mario_Past64 => "end_mario_past64",
+            End_Mario_PastUsa => "end_mario_pastusa",
+            End_Mario_PastX => "end_mario_pastx",
+            End_Mario_Rainbow => "end_mario_rainbow",
+            End_Mario_Uworld => "end_mario_uworld",
+            End_MarioBros => "end_mariobros",
+            End_Metroid_Kraid => "end_metroid_kraid",
+            End_Metroid_Norfair => "end_metroid_norfair",
+            End_Metroid_Orpheon => "end_metroid_orpheon",
+            End_Metroid_ZebesDx => "end_metroid_zebesdx",
+            End_MG_Shadowmoses => "end_mg_shadowmoses",
+            End_Mother_Fourside => "end_mother_fourside",
+            End_Mother_Magicant => "end_mother_magicant",
+            End_Mother_Newpork => "end_mother_newpork",
+            End_Mother_Onett => "end_mother_onett",
+            End_Nintendogs => "end_nintendogs",
+            End_Pac_Land => "end_pac_land",
+            End_Pickel_World => "end_pickel_world",
+            End_Pictochat2 => "end_pictochat2",
+            End_Pikmin_Garden => "end_pikmin_garden",
+            End_Pikmin_Planet => "end_pikmin_planet",
+            End_Pilotwings => "end_pilotwings",
+            End_Plankton => "end_plankton",
+            End_Poke_Kalos => "end_poke_kalos",
+            End_Poke_Stadium => "end_poke_stadium",
+            End_Poke_Stadium2 => "end_poke_stadium2",
+            End_Poke_Tengam => "end_poke_tengam",
+            End_Poke_Tower => "end_poke_tower",
+            End_Poke_Unova => "end_poke_unova",
+            End_Poke_Yamabuki => "end_poke_yamabuki",
+            End_PunchOutSB => "end_punchoutsb",
+            End_PunchOutW => "end_punchoutw",
+            End_Rock_Wily => "end_rock_wily",
+            End_SF_Suzaku => "end_sf_suzaku",
+            End_Sonic_Greenhill => "end_sonic_greenhill",
+            End_Sonic_Windyhill => "end_sonic_windyhill",
+            End_Spla_Parking => "end_spla_parking",
+            End_StreetPass => "end_streetpass",
+            End_Tantan_Spring => "end_tantan_spring",
+            End_Tomodachi => "end_tomodachi",
+            End_Trail_Castle => "end_trail_castle",
+            End_Wario_Gamer => "end_wario_gamer",
+            End_Wario_Madein => "end_wario_madein",
+            End_WiiFit => "end_wiifit",
+            End_WreckingCrew => "end_wreckingcrew",
+            End_WufuIsland => "end_wufuisland",
+            End_Xeno_Alst => "end_xeno_alst",
+            End_Xeno_Gaur => "end_xeno_gaur",
+            End_Yoshi_CartBoard => "end_yoshi_cartboard",
+            End_Yoshi_Island => "end_yoshi_island",
+            End_Yoshi_Story => "end_yoshi_story",
+            End_Yoshi_Yoster => "end_yoshi_yoster",
+            End_Zelda_Gerudo => "end_zelda_gerudo",
+            End_Zelda_Greatbay => "end_zelda_greatbay",
+            End_Zelda_Hyrule => "end_zelda_hyrule",
+            End_Zelda_Oldin => "end_zelda_oldin",
+            End_Zelda_Pirates => "end_zelda_pirates",
+            End_Zelda_Skyward => "end_zelda_skyward",
+            End_Zelda_Temple => "end_zelda_temple",
+            End_Zelda_Tower => "end_zelda_tower",
+            End_Zelda_Train => "end_zelda_train",
+            End => "end",
+            FE_Arena => "fe_arena",
+            FE_Colloseum => "fe_colloseum",
+            FE_Shrine => "fe_shrine",
+            FE_Siege => "fe_siege",
+            FF_Cave => "ff_cave",
+            FF_Midgar => "ff_midgar",
+            FlatZoneX => "flatzonex",
+            Fox_Corneria => "fox_corneria",
+            Fox_LylatCruise => "fox_lylatcruise",
+            Fox_Venom => "fox_venom",
+            Fzero_Bigblue => "fzero_bigblue",
+            Fzero_Mutecity3DS => "fzero_mutecity3ds",
+            Fzero_Porttown => "fzero_porttown",
+            HomerunContest => "homeruncontest",
+            Icarus_Angeland => "icarus_angeland",
+            Icarus_SkyWorld => "icarus_skyworld",
+            Icarus_Uprising => "icarus_uprising",
+            Ice_Top => "ice_top",
+            Invalid => "invalid",
+            Jack_Mementoes => "jack_mementoes",
+            Kart_CircuitFor => "kart_circuitfor",
+            Kart_CircuitX => "kart_circuitx",
+            Kirby_Cave => "kirby_cave",
+            Kirby_Fountain => "kirby_fountain",
+            Kirby_Gameboy => "kirby_gameboy",
+            Kirby_Greens => "kirby_greens",
+            Kirby_Halberd => "kirby_halberd",
+            Kirby_Pupupu64 => "kirby_pupupu64",
+            LuigiMansion => "luigimansion",
+            Mario_3DLand => "mario_3dland",
+            Mario_Castle64 => "mario_castle64",
+            Mario_CastleDx => "mario_castledx",
+            Mario_Dolpic => "mario_dolpic",
+            Mario_Galaxy => "mario_galaxy",
+            Mario_Maker => "mario_maker",
+            Mario_NewBros2 => "mario_newbros2",
+            Mario_Odyssey => "mario_odyssey",
+            Mario_Paper => "mario_paper",
+            Mario_Past64 => "mario_past64",
+            Mario_PastUsa => "mario_pastusa",
+            Mario_PastX => "mario_pastx",
+            Mario_Rainbow => "mario_rainbow",
+            Mario_Uworld => "mario_uworld",
+            MarioBros => "mariobros",
+            Metroid_Kraid => "metroid_kraid",
+            Metroid_Norfair => "metroid_norfair",
+            Metroid_Orpheon => "metroid_orpheon",
+            Metroid_ZebesDx => "metroid_zebesdx",
+            MG_Shadowmoses => "mg_shadowmoses",
+            Mother_Fourside => "mother_fourside",
+            Mother_Magicant => "mother_magicant",
+            Mother_Newpork => "mother_newpork",
+            Mother_Onett => "mother_onett",
+            Nintendogs => "nintendogs",
+            Pac_Land => "pac_land",
+            PhotoStage => "photostage",
+            Pickel_World => "pickel_world",
+            Pictochat2 => "pictochat2",
+            Pikmin_Garden => "pikmin_garden",
+            Pikmin_Planet => "pikmin_planet",
+            Pilotwings => "pilotwings",
+            Plankton => "plankton",
+            Poke_Kalos => "poke_kalos",
+            Poke_Stadium => "poke_stadium",
+            Poke_Stadium2 => "poke_stadium2",
+            Poke_Tengam => "poke_tengam",
+            Poke_Tower => "poke_tower",
+            Poke_Unova => "poke_unova",
+            Poke_Yamabuki => "poke_yamabuki",
+            PunchOutSB => "punchoutsb",
+            PunchOutW => "punchoutw",
+            ResultStage_Jack => "resultstage_jack",
+            ResultStage => "resultstage",
+            Rock_Wily => "rock_wily",
+            SettingStage => "settingstage",
+            SF_Suzaku => "sf_suzaku",
+            ShamFight => "shamfight",
+            Sonic_Greenhill => "sonic_greenhill",
+            Sonic_Windyhill => "sonic_windyhill",
+            SP_Edit => "sp_edit",
+            SpiritsRoulette => "spiritsroulette",
+            Spla_Parking => "spla_parking",
+            Staffroll => "staffroll",
+            StreetPass => "streetpass",
+            Tantan_Spring => "tantan_spring",
+            TestStage => "teststage",
+            Tomodachi => "tomodachi",
+            Trail_Castle => "trail_castle",
+            Training => "training",
+            Wario_Gamer => "wario_gamer",
+            Wario_Madein => "wario_madein",
+            WiiFit => "wiifit",
+            WreckingCrew => "wreckingcrew",
+            WufuIsland => "wufuisland",
+            Xeno_Alst => "xeno_alst",
+            Xeno_Gaur => "xeno_gaur",
+            Yoshi_CartBoard => "yoshi_cartboard",
+            Yoshi_Island => "yoshi_island",
+            Yoshi_Story => "yoshi_story",
+            Yoshi_Yoster => "yoshi_yoster",
+            Zelda_Gerudo => "zelda_gerudo",
+            Zelda_Greatbay => "zelda_greatbay",
+            Zelda_Hyrule => "zelda_hyrule",
+            Zelda_Oldin => "zelda_oldin",
+            Zelda_Pirates => "zelda_pirates",
+            Zelda_Skyward => "zelda_skyward",
+            Zelda_Temple => "zelda_temple",
+            Zelda_Tower => "zelda_tower",
+            Zelda_Train => "zelda_train",
+            Max => "max",
+        }
+    }
+}
+
+impl TryFrom<u32> for Stage {
+    type Error = UnknownId;
+
+    fn try_from(s: u32) -> Result<Self, Self::Error> {
+        Stage::ALL.get(s as usize).copied().ok_or(UnknownId::Number(s))
+    }
+}
+
+impl FromStr for Stage {
+    type Err = UnknownId;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Stage::ALL.iter()
+            .copied()
+            .find(|x| x.slug().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownId::Slug(s.to_owned()))
+    }
+}
+
+impl Serialize for Stage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match id_format() {
+            IdFormat::Number => serializer.serialize_u32(*self as u32),
+            IdFormat::Slug => serializer.serialize_str(self.slug()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Stage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(IdVisitor::<Stage>::new())
+    }
+}
+
+// unknown values (say, DLC the client doesn't know about yet) become `None` instead of failing the
+// whole message
+struct IdVisitor<T>(core::marker::PhantomData<T>);
+
+impl<T> IdVisitor<T> {
+    fn new() -> Self {
+        IdVisitor(core::marker::PhantomData)
+    }
+}
+
+impl<'de, T> Visitor<'de> for IdVisitor<T>
+where T: TryFrom<u32> + FromStr + Default,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a numeric id or a slug")
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<T, E> {
+        Ok(u32::try_from(v).ok().and_then(|v| T::try_from(v).ok()).unwrap_or_default())
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<T, E> {
+        Ok(u32::try_from(v).ok().and_then(|v| T::try_from(v).ok()).unwrap_or_default())
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        Ok(v.parse().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod ids_tests {
+    use super::*;
+
+    #[test]
+    fn test_all_in_discriminant_order() {
+        for (i, c) in Character::ALL.iter().enumerate() {
+            assert_eq!(*c as usize, i);
+        }
+        for (i, s) in Stage::ALL.iter().enumerate() {
+            assert_eq!(*s as usize, i);
+        }
+        assert_eq!(Character::ALL.len(), Character::Max as usize);
+        assert_eq!(Stage::ALL.len(), Stage::Max as usize);
+    }
+
+    #[test]
+    fn test_slugs() {
+        assert_eq!(Character::Brave.slug(), "brave");
+        assert_eq!(Stage::Battle_DK_Jungle.slug(), "battle_dk_jungle");
+        assert_eq!(Stage::_75m.slug(), "75m");
+        assert_eq!("brave".parse(), Ok(Character::Brave));
+        assert_eq!("BATTLE_DK_JUNGLE".parse(), Ok(Stage::Battle_DK_Jungle));
+        assert_eq!("nope".parse::<Character>(), Err(UnknownId::Slug("nope".to_owned())));
+        assert_eq!(Character::try_from(Character::Max as u32), Err(UnknownId::Number(Character::Max as u32)));
+    }
+
+    #[test]
+    fn test_serde_formats() {
+        assert_eq!(serde_json::to_string(&Character::Brave).unwrap(), (Character::Brave as u32).to_string());
+        let json = with_id_format(IdFormat::Slug, || serde_json::to_string(&(Character::Brave, Stage::Battle_DK_Jungle)).unwrap());
+        assert_eq!(json, "[\"brave\",\"battle_dk_jungle\"]");
+
+        let (c, s): (Character, Stage) = serde_json::from_str(&json).unwrap();
+        assert_eq!((c, s), (Character::Brave, Stage::Battle_DK_Jungle));
+        let c: Character = serde_json::from_str("\"some_future_dlc\"").unwrap();
+        assert_eq!(c, Character::None);
+    }
+}
@@ -1,6 +1,7 @@
 //#![feature(const_mut_refs)]
 use std::sync::atomic::{AtomicU32, AtomicBool, Ordering};
 use serde::{Serialize, Deserialize};
+use std::convert::TryFrom;
 use std::fmt;
 
 mod atomic_f32;
@@ -12,6 +13,43 @@ pub use atomic_arena_id::AtomicArenaId;
 mod atomic_name;
 pub use atomic_name::AtomicName;
 
+mod shared;
+pub use shared::Shared;
+
+mod ids;
+pub use ids::{IdFormat, UnknownId, with_id_format};
+
+mod snapshot;
+pub use snapshot::{InfoSnapshot, PlayerSnapshot};
+
+use clock::Clock;
+use results::MatchEnd;
+use ruleset::Ruleset;
+use stats::Stats;
+use timeline::StockLoss;
+
+#[cfg(feature = "client")]
+pub mod client;
+pub mod backend;
+pub mod clock;
+pub mod config;
+pub mod delta;
+pub mod discovery;
+pub mod events;
+pub mod handshake;
+pub mod http;
+pub mod websocket;
+pub mod protocol;
+pub mod recording;
+pub mod results;
+pub mod ruleset;
+pub mod scenario;
+pub mod server;
+pub mod sets;
+pub mod stats;
+pub mod teams;
+pub mod timeline;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Info {
     pub arena_id: AtomicArenaId,
@@ -20,7 +58,29 @@ pub struct Info {
     pub is_match: AtomicBool,
     pub is_results_screen: AtomicBool,
     pub stage: AtomicU32,
-    pub players: [Player; 8]
+    /// Game frames the plugin has seen. Only advances while fighters are loaded.
+    #[serde(default)]
+    pub frame: AtomicU32,
+    /// How the last match ended. Set with the placements once the results are in, cleared when
+    /// the next match starts.
+    #[serde(default)]
+    pub match_end: Shared<MatchEnd>,
+    /// Every stock lost in the current match, emptied when the next one starts.
+    #[serde(default)]
+    pub stock_losses: Shared<Vec<StockLoss>>,
+    /// Whether teammates can hurt each other, from the match's rules.
+    #[serde(default)]
+    pub friendly_fire: AtomicBool,
+    /// Time left and elapsed, and the match mode. Prefer it over `remaining_frames`.
+    #[serde(default)]
+    pub clock: Shared<Clock>,
+    /// Of the current or last match, if the plugin could read it.
+    #[serde(default)]
+    pub ruleset: Shared<Option<Ruleset>>,
+    pub players: [Player; 8],
+    /// `frame` at the current match's GO.
+    #[serde(skip)]
+    pub(crate) go_frame: Shared<Option<u32>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -37,12 +97,25 @@ pub struct Player {
     pub y: AtomicF32,
     pub hero_menu_open: AtomicBool,
     pub hero_menu_selected: AtomicBool,
-    pub hero_menu_selection: AtomicU32
+    pub hero_menu_selection: AtomicU32,
+    /// Where the player finished the last match, 1 for first. 0 until the results are in.
+    #[serde(default)]
+    pub placement: AtomicU32,
+    /// Totals for the current match, reset when it starts.
+    #[serde(default)]
+    pub stats: Shared<Stats>,
+    /// Team battles only. The game numbers teams by color.
+    #[serde(default)]
+    pub team: Shared<Option<u32>>,
+    /// `Info::frame` when the stock in play started.
+    #[serde(skip)]
+    pub(crate) stock_started: Shared<Option<u32>>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 #[repr(u32)]
 pub enum Character {
+    #[default]
     None = 0,
     Bayonetta,
     Brave,
@@ -146,9 +219,10 @@ pub enum Character {
 
 // see `Character` for how this should be used
 #[allow(non_camel_case_types)]
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 #[repr(u32)]
 pub enum Stage {
+    #[default]
     None = 0,
     _75m,
     Animal_City,
@@ -1005,6 +1079,20 @@ impl Stage {
     }
 }
 
+impl Character {
+    /// Converts a raw id as stored in `Player::character`, falling back to `None` if out of range.
+    pub fn from_u32(c: u32) -> Self {
+        Self::try_from(c).unwrap_or(Character::None)
+    }
+}
+
+impl Stage {
+    /// Converts a raw id as stored in `Info::stage`, falling back to `None` if out of range.
+    pub fn from_u32(s: u32) -> Self {
+        Self::try_from(s).unwrap_or(Stage::None)
+    }
+}
+
 impl Info {
     pub const fn new() -> Self {
         Self {
@@ -1014,6 +1102,12 @@ impl Info {
             is_match: AtomicBool::new(false),
             is_results_screen: AtomicBool::new(false),
             stage: AtomicU32::new(Stage::None as u32),
+            frame: AtomicU32::new(0),
+            match_end: Shared::new(MatchEnd::None),
+            stock_losses: Shared::new(Vec::new()),
+            friendly_fire: AtomicBool::new(false),
+            clock: Shared::new(Clock::new()),
+            ruleset: Shared::new(None),
             players: [
                 Player::new(),
                 Player::new(),
@@ -1022,8 +1116,9 @@ impl Info {
                 Player::new(),
                 Player::new(),
                 Player::new(),
-                Player::new()
-            ]
+                Player::new(),
+            ],
+            go_frame: Shared::new(None),
         }
     }
 
@@ -1035,19 +1130,50 @@ impl Info {
         self.remaining_frames.load(Ordering::SeqCst)
     }
 
+    pub fn frame(&self) -> u32 {
+        self.frame.load(Ordering::SeqCst)
+    }
+
     pub fn is_match(&self) -> bool {
         self.is_match.load(Ordering::SeqCst)
     }
 
+    pub fn current_menu(&self) -> u32 {
+        self.current_menu.load(Ordering::SeqCst)
+    }
+
+    pub fn is_results_screen(&self) -> bool {
+        self.is_results_screen.load(Ordering::SeqCst)
+    }
+
     pub fn stage(&self) -> Stage {
-        let s = self.stage.load(Ordering::SeqCst);
-        if (0..Stage::Max as u32).contains(&s) {
-            unsafe {
-                core::mem::transmute(s)
-            }
-        } else {
-            Stage::None
-        }
+        Stage::from_u32(self.stage.load(Ordering::SeqCst))
+    }
+
+    pub fn match_end(&self) -> MatchEnd {
+        self.match_end.load()
+    }
+
+    pub fn stock_losses(&self) -> Vec<StockLoss> {
+        self.stock_losses.load()
+    }
+
+    pub fn friendly_fire(&self) -> bool {
+        self.friendly_fire.load(Ordering::SeqCst)
+    }
+
+    pub fn clock(&self) -> Clock {
+        self.clock.load()
+    }
+
+    pub fn ruleset(&self) -> Option<Ruleset> {
+        self.ruleset.load()
+    }
+}
+
+impl Default for Info {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -1066,19 +1192,24 @@ impl Player {
             y: AtomicF32::new(0.),
             hero_menu_open: AtomicBool::new(false),
             hero_menu_selected: AtomicBool::new(false),
-            hero_menu_selection: AtomicU32::new(0)
+            hero_menu_selection: AtomicU32::new(0),
+            placement: AtomicU32::new(0),
+            stats: Shared::new(Stats::new()),
+            team: Shared::new(None),
+            stock_started: Shared::new(None),
         }
     }
 
+    pub fn is_in_game(&self) -> bool {
+        self.is_in_game.load(Ordering::SeqCst)
+    }
+
+    pub fn name(&self) -> Option<String> {
+        self.name.load_string(Ordering::SeqCst)
+    }
+
     pub fn character(&self) -> Character {
-        let c = self.character.load(Ordering::SeqCst);
-        if (0..Character::Max as u32).contains(&c) {
-            unsafe {
-                core::mem::transmute(c)
-            }
-        } else {
-            Character::None
-        }
+        Character::from_u32(self.character.load(Ordering::SeqCst))
     }
 
     pub fn damage(&self) -> f32 {
@@ -1102,11 +1233,11 @@ impl Player {
     }
 
     pub fn x(&self) -> f32 {
-        self.damage.load(Ordering::SeqCst)
+        self.x.load(Ordering::SeqCst)
     }
 
     pub fn y(&self) -> f32 {
-        self.damage.load(Ordering::SeqCst)
+        self.y.load(Ordering::SeqCst)
     }
     
     pub fn hero_menu_open(&self) -> bool {
@@ -1120,6 +1251,24 @@ impl Player {
     pub fn hero_menu_selection(&self) -> u32 {
         self.hero_menu_selection.load(Ordering::SeqCst)
     }
+
+    pub fn placement(&self) -> u32 {
+        self.placement.load(Ordering::SeqCst)
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats.load()
+    }
+
+    pub fn team(&self) -> Option<u32> {
+        self.team.load()
+    }
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[cfg(test)]
@@ -1155,14 +1304,21 @@ mod shared_tests {
     }
 
     #[test]
+    #[allow(clippy::declare_interior_mutable_const, clippy::borrow_interior_mutable_const)]
     fn stage_test() {
         const TEST_INFO: Info = Info {
             arena_id: AtomicArenaId::new(Some([b'A', b'A', b'A', b'A', b'A'])),
             is_match: AtomicBool::new(true),
             remaining_frames: AtomicU32::new(3),
             current_menu: AtomicU32::new(3),
-            is_results_screen: AtomicU32::new(3),
+            is_results_screen: AtomicBool::new(false),
             stage: AtomicU32::new(Stage::Plankton as u32),
+            frame: AtomicU32::new(0),
+            match_end: Shared::new(MatchEnd::None),
+            stock_losses: Shared::new(Vec::new()),
+            friendly_fire: AtomicBool::new(false),
+            clock: Shared::new(Clock::new()),
+            ruleset: Shared::new(None),
             players: [
                 Player::new(),
                 Player::new(),
@@ -1172,7 +1328,8 @@ mod shared_tests {
                 Player::new(),
                 Player::new(),
                 Player::new(),
-            ]
+            ],
+            go_frame: Shared::new(None),
         };
 
         assert_eq!(TEST_INFO.stage(), Stage::Plankton);
@@ -0,0 +1,388 @@
+//! Framing shared by the plugin and its clients: newline-delimited JSON by default, or
+//! length-prefixed MessagePack for clients that pick [`Encoding::MessagePack`] in their hello.
+//!
+//! The server pushes one JSON message per line. Clients may write [`Command`]s back over the same
+//! connection, also one per line, and the server answers each with a [`Reply`] (or, for
+//! [`Command::GetInfo`], a regular `Info` line). See [`handshake`](crate::handshake) for the
+//! messages that open a connection.
+use serde::{Serialize, Deserialize};
+
+use crate::{InfoSnapshot, IdFormat};
+use crate::delta::Delta;
+use crate::events::Event;
+use crate::handshake::{Hello, ClientHello};
+
+/// Longest line either side will buffer before giving up on the peer, unless it says otherwise.
+pub const MAX_FRAME_LEN: usize = 0x4000;
+
+/// Longest message clients buffer from the server. Full infos carry every stock lost in the
+/// match, which long time battles take well past [`MAX_FRAME_LEN`].
+pub const MAX_SERVER_FRAME_LEN: usize = 0x100000;
+
+/// Shortest push interval a client may request.
+pub const MIN_INTERVAL_MS: u64 = 16;
+
+/// How the server encodes messages to a client. Commands from the client are always JSON lines.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[default]
+    Json,
+    /// MessagePack with named fields, each message prefixed by its length as a big-endian `u32`.
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    /// Answer to the server's [`Hello`], usually sent first.
+    Hello(ClientHello),
+    Ping,
+    GetInfo,
+    SetInterval { millis: u64 },
+    Pause,
+    Resume,
+    Version,
+    /// Switch between full `Info` lines and [`Delta`] messages.
+    SetDelta { enabled: bool },
+    /// Send `Character`/`Stage` as numbers (the default) or as stable slugs.
+    SetIdFormat { format: IdFormat },
+    /// Push on every `n`th game frame instead of on a timer. `0` goes back to the timer.
+    SetFrameSync { every_n_frames: u32 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "reply", rename_all = "snake_case")]
+pub enum Reply {
+    /// The settings now in effect after a [`Command::Hello`].
+    Welcome(ClientHello),
+    Pong,
+    IntervalSet { millis: u64 },
+    Paused,
+    Resumed,
+    Version { version: String },
+    DeltaSet { enabled: bool },
+    IdFormatSet { format: IdFormat },
+    FrameSyncSet { every_n_frames: u32 },
+    Error { message: String },
+}
+
+/// Anything the server may write on a line.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ServerMessage {
+    Hello { hello: Hello },
+    Event { event: Event },
+    Reply(Reply),
+    Delta(Delta),
+    Info(Box<InfoSnapshot>),
+}
+
+#[derive(Debug)]
+pub enum FrameError {
+    TooLong,
+    Json(serde_json::Error),
+    MessagePack(rmp_serde::decode::Error),
+}
+
+impl From<serde_json::Error> for FrameError {
+    fn from(err: serde_json::Error) -> Self {
+        FrameError::Json(err)
+    }
+}
+
+impl From<rmp_serde::decode::Error> for FrameError {
+    fn from(err: rmp_serde::decode::Error) -> Self {
+        FrameError::MessagePack(err)
+    }
+}
+
+const LEN_PREFIX: usize = 4;
+
+/// Serializes `msg` as a single newline-terminated frame.
+pub fn encode<T: Serialize + ?Sized>(msg: &T) -> Vec<u8> {
+    let mut data = serde_json::to_vec(msg).unwrap();
+    data.push(b'\n');
+    data
+}
+
+/// Serializes `msg` as a single frame in `encoding`: a JSON line or a length-prefixed MessagePack
+/// message.
+pub fn encode_as<T: Serialize + ?Sized>(encoding: Encoding, msg: &T) -> Vec<u8> {
+    match encoding {
+        Encoding::Json => encode(msg),
+        Encoding::MessagePack => {
+            let body = rmp_serde::to_vec_named(msg).unwrap();
+            let mut data = Vec::with_capacity(LEN_PREFIX + body.len());
+            data.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            data.extend_from_slice(&body);
+            data
+        }
+    }
+}
+
+/// Strips the newline or length prefix [`encode_as`] added, for transports with their own
+/// message boundaries such as WebSocket.
+pub fn frame_payload(encoding: Encoding, frame: &[u8]) -> &[u8] {
+    match encoding {
+        Encoding::Json => frame.strip_suffix(b"\n").unwrap_or(frame),
+        Encoding::MessagePack => frame.get(LEN_PREFIX..).unwrap_or_default(),
+    }
+}
+
+pub fn decode_command(frame: &[u8]) -> Result<Command, FrameError> {
+    Ok(serde_json::from_slice(frame)?)
+}
+
+pub fn decode_server_message(frame: &[u8]) -> Result<ServerMessage, FrameError> {
+    Ok(serde_json::from_slice(frame)?)
+}
+
+/// Decodes a frame popped off a [`FrameBuffer`] set to the same `encoding`.
+pub fn decode_server_message_as(encoding: Encoding, frame: &[u8]) -> Result<ServerMessage, FrameError> {
+    match encoding {
+        Encoding::Json => decode_server_message(frame),
+        Encoding::MessagePack => Ok(rmp_serde::from_slice(frame)?),
+    }
+}
+
+/// Accumulates bytes from a stream socket and splits them into frames. Frames longer than the
+/// limit, [`MAX_FRAME_LEN`] by default, are reported once as [`FrameError::TooLong`] and skipped
+/// whole, so the frames after them still come out right.
+pub struct FrameBuffer {
+    buf: Vec<u8>,
+    encoding: Encoding,
+    max_len: usize,
+    /// Bytes of a too long MessagePack frame still to be thrown away.
+    skip_len: usize,
+    /// Throwing away a too long JSON line up to its newline.
+    skip_line: bool,
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self { buf: Vec::new(), encoding: Encoding::default(), max_len: MAX_FRAME_LEN, skip_len: 0, skip_line: false }
+    }
+}
+
+impl FrameBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes frames up to `max_len` long, such as [`MAX_SERVER_FRAME_LEN`] for server messages.
+    pub fn with_max_len(max_len: usize) -> Self {
+        Self { max_len, ..Self::default() }
+    }
+
+    pub fn with_encoding(encoding: Encoding) -> Self {
+        Self { encoding, ..Self::default() }
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Switches how the rest of the stream is split, e.g. once the server has welcomed a
+    /// MessagePack client. Bytes already buffered are split the new way.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Pops the next complete frame, without its trailing newline or length prefix. Empty lines
+    /// are skipped.
+    pub fn next_frame(&mut self) -> Option<Result<Vec<u8>, FrameError>> {
+        if self.encoding == Encoding::MessagePack {
+            return self.next_prefixed_frame();
+        }
+
+        loop {
+            match self.buf.iter().position(|&b| b == b'\n') {
+                None if self.skip_line => {
+                    self.buf.clear();
+                    return None;
+                }
+                Some(end) if self.skip_line => {
+                    self.buf.drain(..=end);
+                    self.skip_line = false;
+                }
+                Some(0) => {
+                    self.buf.remove(0);
+                }
+                Some(end) if end > self.max_len => {
+                    self.buf.drain(..=end);
+                    return Some(Err(FrameError::TooLong));
+                }
+                Some(end) => {
+                    let mut frame: Vec<u8> = self.buf.drain(..=end).collect();
+                    frame.pop();
+                    if frame.last() == Some(&b'\r') {
+                        frame.pop();
+                    }
+                    return Some(Ok(frame));
+                }
+                None if self.buf.len() > self.max_len => {
+                    self.buf.clear();
+                    self.skip_line = true;
+                    return Some(Err(FrameError::TooLong));
+                }
+                None => return None,
+            }
+        }
+    }
+
+    fn next_prefixed_frame(&mut self) -> Option<Result<Vec<u8>, FrameError>> {
+        if self.skip_len > 0 {
+            let skipped = self.skip_len.min(self.buf.len());
+            self.buf.drain(..skipped);
+            self.skip_len -= skipped;
+            if self.skip_len > 0 {
+                return None;
+            }
+        }
+
+        let mut len = [0; LEN_PREFIX];
+        len.copy_from_slice(self.buf.get(..LEN_PREFIX)?);
+        let len = u32::from_be_bytes(len) as usize;
+
+        if len > self.max_len {
+            self.skip_len = LEN_PREFIX + len;
+            return Some(Err(FrameError::TooLong));
+        }
+        if self.buf.len() < LEN_PREFIX + len {
+            return None;
+        }
+
+        let frame = self.buf[LEN_PREFIX..LEN_PREFIX + len].to_vec();
+        self.buf.drain(..LEN_PREFIX + len);
+        Some(Ok(frame))
+    }
+}
+
+#[cfg(test)]
+mod protocol_tests {
+    use super::*;
+
+    #[test]
+    fn test_command_round_trip() {
+        let frame = encode(&Command::SetInterval { millis: 50 });
+        assert_eq!(frame, b"{\"command\":\"set_interval\",\"millis\":50}\n");
+        let command = decode_command(&frame[..frame.len() - 1]).unwrap();
+        assert_eq!(command, Command::SetInterval { millis: 50 });
+    }
+
+    #[test]
+    fn test_frame_buffer_split() {
+        let mut frames = FrameBuffer::new();
+        frames.extend(b"{\"command\":\"ping\"}\n\n{\"comm");
+        assert_eq!(frames.next_frame().unwrap().unwrap(), b"{\"command\":\"ping\"}");
+        assert!(frames.next_frame().is_none());
+        frames.extend(b"and\":\"pause\"}\r\n");
+        let command = decode_command(&frames.next_frame().unwrap().unwrap()).unwrap();
+        assert_eq!(command, Command::Pause);
+    }
+
+    #[test]
+    fn test_frame_too_long() {
+        let long = vec![b'x'; MAX_FRAME_LEN + 1];
+        let mut frames = FrameBuffer::new();
+        frames.extend(&long);
+        assert!(matches!(frames.next_frame(), Some(Err(FrameError::TooLong))));
+        // the rest of the line goes too, and the next one comes out whole
+        frames.extend(b"xxx\n{\"command\":\"ping\"}\n");
+        assert_eq!(frames.next_frame().unwrap().unwrap(), b"{\"command\":\"ping\"}");
+        assert!(frames.next_frame().is_none());
+
+        // a long line arriving at once is too long all the same
+        frames.extend(&long);
+        frames.extend(b"\n{}\n");
+        assert!(matches!(frames.next_frame(), Some(Err(FrameError::TooLong))));
+        assert_eq!(frames.next_frame().unwrap().unwrap(), b"{}");
+
+        let mut frames = FrameBuffer::with_encoding(Encoding::MessagePack);
+        let mut stream = encode_as(Encoding::MessagePack, &String::from_utf8(long.clone()).unwrap());
+        stream.extend(encode_as(Encoding::MessagePack, &Reply::Pong));
+        frames.extend(&stream[..100]);
+        assert!(matches!(frames.next_frame(), Some(Err(FrameError::TooLong))));
+        assert!(frames.next_frame().is_none());
+        frames.extend(&stream[100..]);
+        let frame = frames.next_frame().unwrap().unwrap();
+        assert!(matches!(decode_server_message_as(Encoding::MessagePack, &frame).unwrap(), ServerMessage::Reply(Reply::Pong)));
+        assert!(frames.next_frame().is_none());
+
+        let mut frames = FrameBuffer::with_max_len(MAX_SERVER_FRAME_LEN);
+        frames.extend(&long);
+        frames.extend(b"\n");
+        assert_eq!(frames.next_frame().unwrap().unwrap().len(), MAX_FRAME_LEN + 1);
+    }
+
+    #[test]
+    fn test_server_message() {
+        let hello = ServerMessage::Hello {
+            hello: Hello {
+                plugin_version: "0.3.0".to_owned(),
+                schema_version: crate::handshake::SCHEMA_VERSION,
+                game_version: Some("13.0.2".to_owned()),
+                hooks: Default::default(),
+            }
+        };
+        assert!(matches!(decode_server_message(&encode(&hello)).unwrap(), ServerMessage::Hello { .. }));
+
+        let event = ServerMessage::Event { event: Event::MatchEnded };
+        assert_eq!(encode(&event), b"{\"event\":{\"type\":\"match_ended\"}}\n");
+        assert!(matches!(decode_server_message(&encode(&event)).unwrap(), ServerMessage::Event { .. }));
+
+        let reply = encode(&Reply::Pong);
+        assert!(matches!(decode_server_message(&reply).unwrap(), ServerMessage::Reply(Reply::Pong)));
+
+        let info = encode(&crate::Info::new());
+        assert!(matches!(decode_server_message(&info).unwrap(), ServerMessage::Info(_)));
+
+        let mut encoder = crate::delta::DeltaEncoder::new();
+        let delta = encode(&encoder.encode(&InfoSnapshot::default()).unwrap());
+        assert!(matches!(decode_server_message(&delta).unwrap(), ServerMessage::Delta(Delta::Full { .. })));
+
+        let mut info = InfoSnapshot::default();
+        info.players[3].stocks = 2;
+        let patch = encoder.encode(&info).unwrap();
+        assert_eq!(decode_server_message(&encode(&patch)).unwrap(), ServerMessage::Delta(patch));
+    }
+
+    #[test]
+    fn test_message_pack() {
+        let mut info = InfoSnapshot { is_match: true, stage: crate::Stage::Battle_DK_Jungle, ..Default::default() };
+        info.players[1].damage = 42.5;
+        info.players[1].name = Some("sharlot".to_owned());
+
+        let mut encoder = crate::delta::DeltaEncoder::new();
+        encoder.encode(&InfoSnapshot::default());
+        let patch = encoder.encode(&info).unwrap();
+
+        let mut stream = encode_as(Encoding::MessagePack, &info);
+        assert!(stream.len() < encode(&info).len());
+        stream.extend(encode_as(Encoding::MessagePack, &ServerMessage::Event { event: Event::MatchEnded }));
+        stream.extend(encode_as(Encoding::MessagePack, &patch));
+
+        let mut frames = FrameBuffer::with_encoding(Encoding::MessagePack);
+        frames.extend(&stream[..3]);
+        assert!(frames.next_frame().is_none());
+        frames.extend(&stream[3..]);
+
+        let mut decode = || decode_server_message_as(Encoding::MessagePack, &frames.next_frame().unwrap().unwrap()).unwrap();
+        assert!(matches!(decode(), ServerMessage::Info(decoded) if *decoded == info));
+        assert!(matches!(decode(), ServerMessage::Event { event: Event::MatchEnded }));
+        assert!(matches!(decode(), ServerMessage::Delta(decoded) if decoded == patch));
+        assert!(frames.next_frame().is_none());
+    }
+
+    #[test]
+    fn test_frame_payload() {
+        assert_eq!(frame_payload(Encoding::Json, &encode(&Reply::Pong)), b"{\"reply\":\"pong\"}");
+        let frame = encode_as(Encoding::MessagePack, &Reply::Pong);
+        assert_eq!(frame_payload(Encoding::MessagePack, &frame), &frame[4..]);
+    }
+}
@@ -0,0 +1,80 @@
+use smush_info_shared::discovery;
+use smush_info_shared::recording::Recorder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::Duration;
+
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+fn find_switch(arg: Option<String>) -> Option<SocketAddr> {
+    match arg {
+        Some(arg) => match arg.parse::<IpAddr>() {
+            Ok(ip) => Some(SocketAddr::new(ip, 4242)),
+            Err(_) => arg.parse().ok(),
+        },
+        None => discovery::discover(DISCOVERY_TIMEOUT).ok()?.iter().find_map(|switch| switch.addr()),
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let path = match args.next() {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: smush_info_record <file> [ip]");
+            std::process::exit(1);
+        }
+    };
+
+    let addr = match find_switch(args.next()) {
+        Some(addr) => addr,
+        None => {
+            eprintln!("Couldn't find a Switch. Pass its IP or check the plugin is running on the same network.");
+            std::process::exit(1);
+        }
+    };
+
+    let file = match File::create(&path) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("Couldn't create {}: {}", path, err);
+            std::process::exit(1);
+        }
+    };
+    let mut recorder = Recorder::new(BufWriter::new(file));
+
+    // keep going across disconnects so a flaky connection doesn't end a set's recording; the gap
+    // shows up in the timestamps
+    let mut packets = 0usize;
+    loop {
+        let stream = match TcpStream::connect(addr) {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Couldn't connect to {}: {}", addr, err);
+                std::thread::sleep(RECONNECT_DELAY);
+                continue
+            }
+        };
+        println!("Recording {} to {}", addr, path);
+
+        for line in BufReader::new(stream).split(b'\n') {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    break
+                }
+            };
+            if let Err(err) = recorder.record(&line) {
+                eprintln!("Couldn't write {}: {}", path, err);
+                std::process::exit(1);
+            }
+            packets += 1;
+        }
+
+        eprintln!("Disconnected after {} packets, reconnecting", packets);
+        std::thread::sleep(RECONNECT_DELAY);
+    }
+}
@@ -0,0 +1,135 @@
+//! Recorded sessions, for developing against a stream without a Switch.
+//!
+//! A recording is a text file with one received line per line, prefixed by the milliseconds since
+//! recording started and a tab, e.g. `161<TAB>{"arena_id":null,...}`.
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// When the line arrived, relative to the start of the recording.
+    pub at: Duration,
+    /// The line as received, without its newline.
+    pub packet: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(io::Error),
+    /// A line didn't start with a timestamp and a tab.
+    Malformed { line: usize },
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingError::Io(err) => write!(f, "{}", err),
+            RecordingError::Malformed { line } => write!(f, "line {} isn't a recorded packet", line),
+        }
+    }
+}
+
+impl From<io::Error> for RecordingError {
+    fn from(err: io::Error) -> Self {
+        RecordingError::Io(err)
+    }
+}
+
+/// Appends packets to a recording, timestamped as they're recorded.
+pub struct Recorder<W: Write> {
+    out: W,
+    start: Instant,
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, start: Instant::now() }
+    }
+
+    pub fn record(&mut self, packet: &[u8]) -> io::Result<()> {
+        self.write_entry(self.start.elapsed(), packet)
+    }
+
+    fn write_entry(&mut self, at: Duration, packet: &[u8]) -> io::Result<()> {
+        write!(self.out, "{}\t", at.as_millis())?;
+        self.out.write_all(packet)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()
+    }
+}
+
+pub fn read_recording(input: impl BufRead) -> Result<Vec<Entry>, RecordingError> {
+    let mut entries = Vec::new();
+    for (i, line) in input.split(b'\n').enumerate() {
+        let line = line?;
+        if line.is_empty() {
+            continue
+        }
+
+        let malformed = RecordingError::Malformed { line: i + 1 };
+        let tab = match line.iter().position(|&b| b == b'\t') {
+            Some(tab) => tab,
+            None => return Err(malformed),
+        };
+        let at = match std::str::from_utf8(&line[..tab]).ok().and_then(|ms| ms.parse().ok()) {
+            Some(ms) => Duration::from_millis(ms),
+            None => return Err(malformed),
+        };
+
+        entries.push(Entry { at, packet: line[tab + 1..].to_vec() });
+    }
+    Ok(entries)
+}
+
+/// How a replay moves from one packet to the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pace {
+    /// Keep the recorded gaps, scaled by a speed multiplier (`2.0` plays twice as fast).
+    Speed(f64),
+    /// Wait for the operator before each packet.
+    Stepped,
+}
+
+impl Pace {
+    /// How long to wait between `prev` and `next`. `None` when stepped.
+    pub fn delay(self, prev: &Entry, next: &Entry) -> Option<Duration> {
+        match self {
+            Pace::Speed(speed) => Some(next.at.saturating_sub(prev.at).div_f64(speed)),
+            Pace::Stepped => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod recording_tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut out = Vec::new();
+        let mut recorder = Recorder::new(&mut out);
+        recorder.write_entry(Duration::from_millis(0), b"{\"hello\":{}}").unwrap();
+        recorder.write_entry(Duration::from_millis(161), b"{\"event\":{\"type\":\"match_ended\"}}").unwrap();
+        assert_eq!(out, b"0\t{\"hello\":{}}\n161\t{\"event\":{\"type\":\"match_ended\"}}\n");
+
+        let entries = read_recording(&out[..]).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1], Entry { at: Duration::from_millis(161), packet: b"{\"event\":{\"type\":\"match_ended\"}}".to_vec() });
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(matches!(read_recording(&b"0\t{}\n\nnope\n"[..]), Err(RecordingError::Malformed { line: 3 })));
+    }
+
+    #[test]
+    fn test_pace() {
+        let prev = Entry { at: Duration::from_millis(100), packet: Vec::new() };
+        let next = Entry { at: Duration::from_millis(260), packet: Vec::new() };
+        assert_eq!(Pace::Speed(1.0).delay(&prev, &next), Some(Duration::from_millis(160)));
+        assert_eq!(Pace::Speed(2.0).delay(&prev, &next), Some(Duration::from_millis(80)));
+        assert_eq!(Pace::Speed(1.0).delay(&next, &prev), Some(Duration::ZERO));
+        assert_eq!(Pace::Stepped.delay(&prev, &next), None);
+    }
+}
@@ -0,0 +1,166 @@
+use smush_info_shared::recording::{self, Entry, Pace};
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const CLIENT_POLL: Duration = Duration::from_millis(50);
+const USAGE: &str = "usage: smush_info_replay <file> [--speed <multiplier> | --step] [--loop] [--port <port>]";
+
+struct Options {
+    path: String,
+    pace: Pace,
+    looping: bool,
+    port: u16,
+}
+
+fn parse_args() -> Option<Options> {
+    let mut options = Options { path: String::new(), pace: Pace::Speed(1.0), looping: false, port: 4242 };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--speed" => match args.next()?.parse() {
+                Ok(speed) if speed > 0.0 => options.pace = Pace::Speed(speed),
+                _ => return None,
+            },
+            "--step" => options.pace = Pace::Stepped,
+            "--loop" => options.looping = true,
+            "--port" => options.port = args.next()?.parse().ok()?,
+            _ if options.path.is_empty() && !arg.starts_with("--") => options.path = arg,
+            _ => return None,
+        }
+    }
+    if options.path.is_empty() {
+        None
+    } else {
+        Some(options)
+    }
+}
+
+type Clients = Arc<Mutex<Vec<TcpStream>>>;
+
+fn is_hello(entry: &Entry) -> bool {
+    matches!(
+        serde_json::from_slice::<serde_json::Value>(&entry.packet),
+        Ok(serde_json::Value::Object(message)) if message.contains_key("hello")
+    )
+}
+
+fn send_line(stream: &mut TcpStream, packet: &[u8]) -> std::io::Result<()> {
+    stream.write_all(packet)?;
+    stream.write_all(b"\n")
+}
+
+/// Accepts clients, greeting each with the recording's hello. Whatever they send is read and
+/// dropped; the replay doesn't answer commands.
+fn accept_clients(listener: TcpListener, hello: Option<Vec<u8>>, clients: Clients) {
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        if let Some(hello) = &hello {
+            if send_line(&mut stream, hello).is_err() {
+                continue
+            }
+        }
+        if let Ok(mut reader) = stream.try_clone() {
+            std::thread::spawn(move || {
+                let mut buf = [0u8; 0x200];
+                while matches!(reader.read(&mut buf), Ok(len) if len > 0) {}
+            });
+        }
+        if let Ok(addr) = stream.peer_addr() {
+            println!("{} connected", addr);
+        }
+        clients.lock().unwrap().push(stream);
+    }
+}
+
+fn broadcast(clients: &Clients, packet: &[u8]) {
+    clients.lock().unwrap().retain_mut(|stream| send_line(stream, packet).is_ok());
+}
+
+fn wait_for_enter() {
+    let mut line = String::new();
+    if std::io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+        std::process::exit(0);
+    }
+}
+
+fn main() {
+    let options = match parse_args() {
+        Some(options) => options,
+        None => {
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    let entries = match File::open(&options.path).map_err(recording::RecordingError::from)
+        .and_then(|file| recording::read_recording(BufReader::new(file)))
+    {
+        Ok(entries) if !entries.is_empty() => entries,
+        Ok(_) => {
+            eprintln!("{} is empty", options.path);
+            std::process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("Couldn't read {}: {}", options.path, err);
+            std::process::exit(1);
+        }
+    };
+
+    let listener = match TcpListener::bind(("0.0.0.0", options.port)) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Couldn't listen on port {}: {}", options.port, err);
+            std::process::exit(1);
+        }
+    };
+
+    // the hello is sent on connect instead, like the plugin does
+    let hello = entries.iter().find(|entry| is_hello(entry)).map(|entry| entry.packet.clone());
+    let clients = Clients::default();
+    {
+        let clients = Arc::clone(&clients);
+        std::thread::spawn(move || accept_clients(listener, hello, clients));
+    }
+
+    println!("Waiting for a client on port {}", options.port);
+    while clients.lock().unwrap().is_empty() {
+        std::thread::sleep(CLIENT_POLL);
+    }
+
+    println!("Replaying {} packets from {}", entries.len(), options.path);
+    if options.pace == Pace::Stepped {
+        println!("Press Enter to send each packet");
+    }
+
+    loop {
+        let mut prev: Option<&Entry> = None;
+        for (i, entry) in entries.iter().enumerate() {
+            if is_hello(entry) {
+                continue
+            }
+            match prev.map(|prev| options.pace.delay(prev, entry)) {
+                Some(Some(delay)) => std::thread::sleep(delay),
+                _ if options.pace == Pace::Stepped => {
+                    print!("[{}/{}] ", i + 1, entries.len());
+                    let _ = std::io::stdout().flush();
+                    wait_for_enter();
+                }
+                _ => (),
+            }
+            broadcast(&clients, &entry.packet);
+            prev = Some(entry);
+        }
+
+        if !options.looping {
+            break
+        }
+        println!("Looping");
+    }
+    println!("Done");
+}
@@ -0,0 +1,252 @@
+//! The outcome of a match, as the game ranked it on the results screen.
+use std::sync::atomic::Ordering;
+use serde::{Serialize, Deserialize};
+
+use crate::{Info, InfoSnapshot};
+use crate::clock::MatchMode;
+
+/// Percent everyone starts sudden death on.
+pub const SUDDEN_DEATH_DAMAGE: f32 = 300.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchEnd {
+    /// No results yet.
+    #[default]
+    None,
+    /// Everyone but the winner (or winning team) ran out of stocks.
+    Stocks,
+    /// Time ran out with more than one player, or team, left.
+    Time,
+    /// The winner took sudden death.
+    SuddenDeath,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub port: usize,
+    /// 1 for first. Tied players share a placement.
+    pub placement: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MatchResult {
+    /// Best first.
+    pub placements: Vec<Placement>,
+    /// Everyone who placed first.
+    pub winners: Vec<usize>,
+    pub end: MatchEnd,
+}
+
+impl InfoSnapshot {
+    /// The last match's result, from when the results screen comes up until the next match.
+    pub fn match_result(&self) -> Option<MatchResult> {
+        if self.match_end == MatchEnd::None {
+            return None;
+        }
+
+        let mut placements: Vec<_> = self.players.iter()
+            .enumerate()
+            .filter(|(_, player)| player.placement > 0)
+            .map(|(port, player)| Placement { port, placement: player.placement })
+            .collect();
+        placements.sort_by_key(|placement| placement.placement);
+        let winners = placements.iter()
+            .take_while(|placement| placement.placement == placements[0].placement)
+            .map(|placement| placement.port)
+            .collect();
+
+        Some(MatchResult { placements, winners, end: self.match_end })
+    }
+}
+
+/// Stores where everyone in the match placed, as `placement` reports it, and how the match ended.
+/// Must run before the players are taken out of the game and the clock stops.
+///
+/// The game's result doesn't say how the match ended, so it's worked out from the clock and the
+/// stocks, which is a heuristic:
+/// * sudden death, if the clock saw it start
+/// * time, in time battles
+/// * time, when the clock ran out with more than one player (or team) still holding stocks
+/// * stocks otherwise, including a last stock taken on the very last frame, when the clock reads
+///   0 but only one side is left
+///
+/// A results screen reached with time on the clock and several sides standing can only come from
+/// the game ending it early, and counts as stocks too.
+pub fn store_match_result(info: &Info, placement: impl Fn(usize) -> Option<u32>) {
+    for (port, player) in info.players.iter().enumerate() {
+        let placement = if player.is_in_game() { placement(port).unwrap_or(0) } else { 0 };
+        player.placement.store(placement, Ordering::SeqCst);
+    }
+
+    let clock = info.clock();
+    let end = if clock.sudden_death {
+        MatchEnd::SuddenDeath
+    } else if clock.mode == MatchMode::Time || (clock.remaining_frames == Some(0) && sides_standing(info) > 1) {
+        MatchEnd::Time
+    } else {
+        MatchEnd::Stocks
+    };
+    info.match_end.store(end);
+}
+
+// teams, or players outside team battles, with stocks left
+fn sides_standing(info: &Info) -> usize {
+    let mut sides = Vec::new();
+    for (port, player) in info.players.iter().enumerate() {
+        let side = player.team().ok_or(port);
+        if player.is_in_game() && player.stocks() > 0 && !sides.contains(&side) {
+            sides.push(side);
+        }
+    }
+    sides.len()
+}
+
+pub fn clear_match_result(info: &Info) {
+    info.match_end.store(MatchEnd::None);
+    for player in &info.players {
+        player.placement.store(0, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod results_tests {
+    use super::*;
+    use crate::clock;
+
+    fn in_match(stocks: &[(u32, f32)]) -> Info {
+        let info = Info::new();
+        for (player, &(stocks, damage)) in info.players.iter().zip(stocks) {
+            player.is_in_game.store(true, Ordering::SeqCst);
+            player.stocks.store(stocks, Ordering::SeqCst);
+            player.damage.store(damage, Ordering::SeqCst);
+        }
+        info
+    }
+
+    #[test]
+    fn test_stocks() {
+        let info = in_match(&[(0, 40.0), (2, 80.0), (0, 10.0)]);
+        assert_eq!(info.snapshot().match_result(), None);
+
+        store_match_result(&info, |port| Some([3, 1, 2][port]));
+        assert_eq!(info.snapshot().match_result(), Some(MatchResult {
+            placements: vec![
+                Placement { port: 1, placement: 1 },
+                Placement { port: 2, placement: 2 },
+                Placement { port: 0, placement: 3 },
+            ],
+            winners: vec![1],
+            end: MatchEnd::Stocks,
+        }));
+
+        clear_match_result(&info);
+        assert_eq!(info.snapshot().match_result(), None);
+        assert_eq!(info.players[1].placement(), 0);
+    }
+
+    #[test]
+    fn test_time() {
+        // on time the game ranks by stocks and then percent, which may tie
+        let info = in_match(&[(2, 40.0), (2, 40.0)]);
+        clock::start(&info);
+        clock::update(&info, 60, true, MatchMode::Stock);
+        clock::update(&info, 0, true, MatchMode::Stock);
+        store_match_result(&info, |_| Some(1));
+        let result = info.snapshot().match_result().unwrap();
+        assert_eq!((result.winners, result.end), (vec![0, 1], MatchEnd::Time));
+
+        // time battles have no stocks to go by, and only ever end on time
+        let info = in_match(&[(0, 40.0), (0, 80.0)]);
+        clock::start(&info);
+        clock::update(&info, 60, true, MatchMode::Time);
+        store_match_result(&info, |port| Some(port as u32 + 1));
+        assert_eq!(info.match_end(), MatchEnd::Time);
+    }
+
+    #[test]
+    fn test_last_frame_ko() {
+        // the last stock went on the frame time ran out, so the clock reads 0 on a stock win
+        let info = in_match(&[(1, 40.0), (0, 120.0)]);
+        clock::start(&info);
+        clock::update(&info, 60, true, MatchMode::Stock);
+        clock::update(&info, 0, true, MatchMode::Stock);
+        store_match_result(&info, |port| Some(port as u32 + 1));
+        assert_eq!(info.match_end(), MatchEnd::Stocks);
+
+        // teammates left standing together still won on stocks
+        let info = in_match(&[(1, 40.0), (2, 10.0), (0, 120.0)]);
+        for (player, team) in info.players.iter().zip(&[0, 0, 1]) {
+            player.team.store(Some(*team));
+        }
+        clock::start(&info);
+        clock::update(&info, 60, true, MatchMode::Stock);
+        clock::update(&info, 0, true, MatchMode::Stock);
+        store_match_result(&info, |port| Some([1, 1, 2][port]));
+        assert_eq!(info.match_end(), MatchEnd::Stocks);
+    }
+
+    #[test]
+    fn test_time_left() {
+        // a stock win with time to spare, and a time battle ended before its time was up
+        let info = in_match(&[(2, 40.0), (0, 120.0)]);
+        clock::start(&info);
+        clock::update(&info, 60, true, MatchMode::Stock);
+        store_match_result(&info, |port| Some(port as u32 + 1));
+        assert_eq!(info.match_end(), MatchEnd::Stocks);
+
+        let info = in_match(&[(0, 40.0), (0, 120.0)]);
+        clock::start(&info);
+        clock::update(&info, 60, true, MatchMode::Time);
+        clock::update(&info, 30, true, MatchMode::Time);
+        store_match_result(&info, |port| Some(port as u32 + 1));
+        assert_eq!(info.match_end(), MatchEnd::Time);
+    }
+
+    #[test]
+    fn test_free_for_all_sudden_death() {
+        // two of three players tied, so only they play on at 300%
+        let info = in_match(&[(2, 300.0), (2, 300.0), (1, 80.0)]);
+        clock::start(&info);
+        clock::update(&info, 60, true, MatchMode::Stock);
+        clock::update(&info, 0, true, MatchMode::Stock);
+        store_match_result(&info, |port| Some([1, 2, 3][port]));
+        assert_eq!(info.match_end(), MatchEnd::SuddenDeath);
+    }
+
+    #[test]
+    fn test_teams() {
+        let info = in_match(&[(2, 40.0), (0, 0.0), (1, 10.0), (0, 0.0)]);
+        for (player, team) in info.players.iter().zip(&[0, 1, 0, 1]) {
+            player.team.store(Some(*team));
+        }
+        store_match_result(&info, |port| Some([1, 2, 1, 2][port]));
+        let result = info.snapshot().match_result().unwrap();
+        assert_eq!((result.winners, result.end), (vec![0, 2], MatchEnd::Stocks));
+    }
+
+    #[test]
+    fn test_sudden_death() {
+        let info = in_match(&[(1, 312.0), (0, 300.0)]);
+        clock::start(&info);
+        clock::update(&info, 60, true, MatchMode::Time);
+        clock::update(&info, 0, true, MatchMode::Time);
+        store_match_result(&info, |port| Some(port as u32 + 1));
+        assert_eq!(info.match_end(), MatchEnd::SuddenDeath);
+    }
+
+    #[test]
+    fn test_serde() {
+        let info = in_match(&[(1, 0.0), (0, 0.0)]);
+        store_match_result(&info, |port| Some(port as u32 + 1));
+        let json = serde_json::to_value(info.snapshot()).unwrap();
+        assert_eq!(json["match_end"], "stocks");
+        assert_eq!(json["players"][1]["placement"], 2);
+
+        // older streams have neither field
+        let mut json = json;
+        json.as_object_mut().unwrap().remove("match_end");
+        let snapshot: InfoSnapshot = serde_json::from_value(json).unwrap();
+        assert_eq!(snapshot.match_end, MatchEnd::None);
+    }
+}
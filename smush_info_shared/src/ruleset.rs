@@ -0,0 +1,51 @@
+//! The rules the current match is played under, as far as they can be read from the fighters.
+//! The time limit and mode are in the [`Clock`](crate::clock::Clock), and team attack is
+//! [`Info::friendly_fire`].
+use serde::{Serialize, Deserialize};
+
+use crate::Info;
+
+/// Missing fields deserialize to `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Ruleset {
+    /// What everyone started with. `None` in time battles.
+    pub stocks: Option<u32>,
+}
+
+/// The match is loading. Called until GO, by when every fighter is in with a full set of stocks.
+/// It's kept until the next match, so it can be checked against the results.
+pub fn start(info: &Info) {
+    let stocks = info.players.iter()
+        .filter(|player| player.is_in_game())
+        .map(|player| player.stocks())
+        .max()
+        .filter(|&stocks| stocks > 0);
+    info.ruleset.store(Some(Ruleset { stocks }));
+}
+
+#[cfg(test)]
+mod ruleset_tests {
+    use super::*;
+    use core::sync::atomic::Ordering;
+
+    #[test]
+    fn test_start() {
+        let info = Info::new();
+        assert_eq!(info.ruleset(), None);
+
+        for player in &info.players[..2] {
+            player.is_in_game.store(true, Ordering::SeqCst);
+            player.stocks.store(3, Ordering::SeqCst);
+        }
+        start(&info);
+        assert_eq!(info.snapshot().ruleset, Some(Ruleset { stocks: Some(3) }));
+
+        // time battles have no stocks
+        for player in &info.players[..2] {
+            player.stocks.store(0, Ordering::SeqCst);
+        }
+        start(&info);
+        assert_eq!(info.ruleset(), Some(Ruleset { stocks: None }));
+    }
+}
@@ -0,0 +1,647 @@
+//! Scripted matches, for exercising overlays and event consumers without a console.
+//!
+//! A scenario is a TOML file of steps that a [`Simulator`] applies to an [`Info`] in order, one game
+//! frame at a time:
+//!
+//! ```toml
+//! name = "Hero ditto"
+//!
+//! [[step]]
+//! action = "pick"
+//! port = 0
+//! character = "brave"
+//! name = "sharlot"
+//!
+//! [[step]]
+//! action = "start_match"
+//! stocks = 3
+//!
+//! [[step]]
+//! action = "wait"
+//! frames = 120
+//! ```
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use serde::{Serialize, Deserialize};
+
+use crate::{Info, Player, Character, Stage};
+use crate::{clock, results, stats, timeline};
+use crate::clock::MatchMode;
+use crate::results::SUDDEN_DEATH_DAMAGE;
+use crate::ruleset;
+use crate::timeline::KoType;
+
+pub const FRAMES_PER_SECOND: u32 = 60;
+
+// what the plugin reports outside of a match (`-1.0 as u32`)
+const NO_REMAINING_FRAMES: u32 = 0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, rename = "step")]
+    pub steps: Vec<Step>,
+}
+
+/// One thing that happens in a scenario. Everything but [`Step::Wait`] takes effect immediately.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    /// Lets this many frames pass before the next step.
+    Wait { frames: u32 },
+    /// Sets `current_menu` to a raw menu id.
+    Menu { menu: u32 },
+    /// A character pick on the CSS.
+    Pick {
+        port: usize,
+        character: Character,
+        #[serde(default)]
+        skin: u32,
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        cpu: bool,
+        /// For team battles.
+        #[serde(default)]
+        team: Option<u32>,
+    },
+    SelectStage { stage: Stage },
+    /// Starts a match with every picked port, with a time limit or none. It's GO right away.
+    /// Time battles leave everyone at 0 stocks, as the game does, whatever `stocks` says.
+    StartMatch {
+        #[serde(default)]
+        stocks: u32,
+        #[serde(default)]
+        time_minutes: Option<u32>,
+        #[serde(default)]
+        friendly_fire: bool,
+        #[serde(default)]
+        mode: MatchMode,
+    },
+    /// Adds to a player's percent, dealt `by` another port. Negative values heal.
+    Damage {
+        port: usize,
+        percent: f32,
+        #[serde(default)]
+        by: Option<usize>,
+    },
+    Move { port: usize, x: f32, y: f32 },
+    /// Takes a stock, KO'd `by` another port through the `ko` blast zone (the bottom by
+    /// default). The player respawns if any are left, or always in time battles.
+    LoseStock {
+        port: usize,
+        #[serde(default)]
+        self_destruct: bool,
+        #[serde(default)]
+        by: Option<usize>,
+        #[serde(default)]
+        ko: Option<KoType>,
+    },
+    OpenHeroMenu { port: usize },
+    ChooseHeroSpell { port: usize, selection: u32 },
+    CloseHeroMenu { port: usize },
+    /// Time runs out on a tie: the tied players go to 300% and play on.
+    SuddenDeath,
+    /// Ends the match without a results screen, as when it's quit out of.
+    EndMatch,
+    /// Ends the match and shows the results screen, with `placements` as the ports from first to
+    /// last. Without them, players are ranked by stocks and then percent, or by KOs minus falls in
+    /// time battles.
+    Results {
+        #[serde(default)]
+        placements: Vec<usize>,
+    },
+    JoinArena { arena_id: String },
+    LeaveArena,
+}
+
+impl Step {
+    fn port(&self) -> Option<usize> {
+        match *self {
+            Step::Pick { port, .. }
+            | Step::Damage { port, .. }
+            | Step::Move { port, .. }
+            | Step::LoseStock { port, .. }
+            | Step::OpenHeroMenu { port }
+            | Step::ChooseHeroSpell { port, .. }
+            | Step::CloseHeroMenu { port } => Some(port),
+            _ => None,
+        }
+    }
+
+    fn by(&self) -> Option<usize> {
+        match *self {
+            Step::Damage { by, .. } | Step::LoseStock { by, .. } => by,
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScenarioError {
+    Io(String),
+    Parse(String),
+    Invalid(String),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(err) => write!(f, "couldn't read scenario: {}", err),
+            ScenarioError::Parse(err) => write!(f, "scenario isn't valid: {}", err),
+            ScenarioError::Invalid(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Scenario {
+    pub fn parse(text: &str) -> Result<Self, ScenarioError> {
+        let scenario: Scenario = toml::from_str(text).map_err(|err| ScenarioError::Parse(err.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        let text = std::fs::read_to_string(path).map_err(|err| ScenarioError::Io(err.to_string()))?;
+        Self::parse(&text)
+    }
+
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        for (i, step) in self.steps.iter().enumerate() {
+            let invalid = |msg: &str| Err(ScenarioError::Invalid(format!("step {}: {}", i + 1, msg)));
+
+            if matches!(step.port(), Some(port) if port >= 8) || matches!(step.by(), Some(port) if port >= 8) {
+                return invalid("port must be 0 to 7");
+            }
+            match step {
+                // unknown slugs deserialize to `None`, so this is usually a typo
+                Step::Pick { character: Character::None, .. } => return invalid("unknown character"),
+                Step::SelectStage { stage: Stage::None } => return invalid("unknown stage"),
+                Step::Results { placements } if placements.iter().any(|&port| port >= 8) => {
+                    return invalid("port must be 0 to 7")
+                }
+                Step::JoinArena { arena_id } if arena_id.len() != 5 || !arena_id.is_ascii() => {
+                    return invalid("arena ids are 5 characters")
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Length in frames, counting only waits.
+    pub fn frames(&self) -> u64 {
+        self.steps.iter()
+            .map(|step| match step {
+                Step::Wait { frames } => *frames as u64,
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+/// Plays a [`Scenario`] into an [`Info`], the way the plugin's hooks would.
+pub struct Simulator {
+    steps: Vec<Step>,
+    next: usize,
+    waiting: u32,
+}
+
+impl Simulator {
+    pub fn new(scenario: Scenario) -> Self {
+        Self { steps: scenario.steps, next: 0, waiting: 0 }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next == self.steps.len() && self.waiting == 0
+    }
+
+    /// Runs one game frame: applies every step due this frame, then advances the match clock.
+    pub fn tick(&mut self, info: &Info) {
+        while self.waiting == 0 && self.next < self.steps.len() {
+            match &self.steps[self.next] {
+                Step::Wait { frames } => self.waiting = *frames,
+                step => apply(info, step),
+            }
+            self.next += 1;
+        }
+
+        if info.is_match() {
+            info.frame.fetch_add(1, Ordering::SeqCst);
+            let remaining = info.remaining_frames();
+            if remaining != u32::MAX {
+                info.remaining_frames.store(remaining.saturating_sub(1), Ordering::SeqCst);
+            }
+            clock::update(info, info.remaining_frames(), true, info.clock().mode);
+        }
+
+        self.waiting = self.waiting.saturating_sub(1);
+    }
+}
+
+fn end_match(info: &Info) {
+    if info.is_match() {
+        stats::end_match(info);
+        clock::stop(info);
+    }
+    info.is_match.store(false, Ordering::SeqCst);
+    info.remaining_frames.store(NO_REMAINING_FRAMES, Ordering::SeqCst);
+    for player in &info.players {
+        player.is_in_game.store(false, Ordering::SeqCst);
+    }
+}
+
+fn placement(info: &Info, placements: &[usize], port: usize) -> Option<u32> {
+    if !placements.is_empty() {
+        return placements.iter().position(|&p| p == port).map(|i| i as u32 + 1);
+    }
+    let time_battle = info.clock().mode == MatchMode::Time;
+    let standing = |player: &Player| if time_battle {
+        let stats = player.stats();
+        (stats.kos as i64 - stats.falls as i64, 0.)
+    } else {
+        (player.stocks() as i64, -player.damage())
+    };
+    let ahead = info.players.iter()
+        .filter(|player| player.is_in_game() && standing(player) > standing(&info.players[port]))
+        .count();
+    Some(ahead as u32 + 1)
+}
+
+fn apply(info: &Info, step: &Step) {
+    let order = Ordering::SeqCst;
+    match step {
+        Step::Wait { .. } => (),
+        Step::Menu { menu } => info.current_menu.store(*menu, order),
+        Step::Pick { port, character, skin, name, cpu, team } => {
+            let player = &info.players[*port];
+            player.character.store(*character as u32, order);
+            player.skin.store(*skin, order);
+            player.name.store_str(name.as_deref(), order);
+            player.is_cpu.store(*cpu, order);
+            player.team.store(*team);
+        }
+        Step::SelectStage { stage } => {
+            info.stage.store(*stage as u32, order);
+            info.is_results_screen.store(false, order);
+        }
+        Step::StartMatch { stocks, time_minutes, friendly_fire, mode } => {
+            let time_battle = *mode == MatchMode::Time;
+            for player in &info.players {
+                let picked = player.character() != Character::None;
+                player.is_in_game.store(picked, order);
+                player.stocks.store(if picked && !time_battle { *stocks } else { 0 }, order);
+                player.self_destructs.store(0, order);
+                player.damage.store(0.0, order);
+                player.hero_menu_open.store(false, order);
+                player.hero_menu_selected.store(false, order);
+            }
+            let remaining = time_minutes.map_or(u32::MAX, |minutes| minutes * 60 * FRAMES_PER_SECOND);
+            info.remaining_frames.store(remaining, order);
+            info.is_results_screen.store(false, order);
+            info.friendly_fire.store(*friendly_fire, order);
+            results::clear_match_result(info);
+            stats::reset(info);
+            timeline::reset(info);
+            clock::start(info);
+            clock::update(info, remaining, true, *mode);
+            ruleset::start(info);
+            info.is_match.store(true, order);
+        }
+        Step::Damage { port, percent, by } => {
+            let damage = &info.players[*port].damage;
+            damage.store((damage.load(order) + percent).max(0.0), order);
+            if *percent > 0.0 && info.is_match() {
+                stats::record_damage(info, *port, *percent, *by);
+            }
+        }
+        Step::Move { port, x, y } => {
+            info.players[*port].x.store(*x, order);
+            info.players[*port].y.store(*y, order);
+        }
+        Step::LoseStock { port, self_destruct, by, ko } => {
+            let player = &info.players[*port];
+            stats::record_fall(info, *port, player.damage(), *by);
+            timeline::record(info, *port, player.damage(), *by, ko.unwrap_or(KoType::Bottom));
+            player.stocks.store(player.stocks().saturating_sub(1), order);
+            player.damage.store(0.0, order);
+            if *self_destruct {
+                player.self_destructs.fetch_add(1, order);
+            }
+            if player.stocks() > 0 || info.clock().mode == MatchMode::Time {
+                stats::start_stock(info, *port);
+            }
+        }
+        Step::OpenHeroMenu { port } => {
+            let player = &info.players[*port];
+            player.hero_menu_selection.store(0, order);
+            player.hero_menu_selected.store(false, order);
+            player.hero_menu_open.store(true, order);
+        }
+        Step::ChooseHeroSpell { port, selection } => {
+            let player = &info.players[*port];
+            player.hero_menu_selection.store(*selection, order);
+            player.hero_menu_selected.store(true, order);
+        }
+        Step::CloseHeroMenu { port } => {
+            let player = &info.players[*port];
+            player.hero_menu_selected.store(false, order);
+            player.hero_menu_open.store(false, order);
+        }
+        Step::SuddenDeath => {
+            for port in clock::tied_ports(info, info.clock().mode) {
+                info.players[port].damage.store(SUDDEN_DEATH_DAMAGE, order);
+            }
+            info.remaining_frames.store(0, order);
+            clock::update(info, 0, true, info.clock().mode);
+        }
+        Step::EndMatch => end_match(info),
+        Step::Results { placements } => {
+            results::store_match_result(info, |port| placement(info, placements, port));
+            end_match(info);
+            info.is_results_screen.store(true, order);
+        }
+        Step::JoinArena { arena_id } => info.arena_id.store_str(Some(arena_id), order),
+        Step::LeaveArena => info.arena_id.store_str(None, order),
+    }
+}
+
+#[cfg(test)]
+mod scenario_tests {
+    use super::*;
+    use crate::events::{Event, EventDeriver};
+    use crate::results::{MatchEnd, MatchResult, Placement};
+    use crate::ruleset::Ruleset;
+    use crate::timeline::StockLoss;
+
+    const SCENARIO: &str = r#"
+        name = "Hero ditto"
+
+        [[step]]
+        action = "join_arena"
+        arena_id = "ABCDE"
+
+        [[step]]
+        action = "pick"
+        port = 0
+        character = "brave"
+        name = "sharlot"
+
+        [[step]]
+        action = "pick"
+        port = 1
+        character = 41
+        skin = 2
+        cpu = true
+
+        [[step]]
+        action = "select_stage"
+        stage = "battlefield"
+
+        [[step]]
+        action = "start_match"
+        stocks = 2
+        time_minutes = 7
+
+        [[step]]
+        action = "wait"
+        frames = 10
+
+        [[step]]
+        action = "damage"
+        port = 1
+        percent = 42.5
+
+        [[step]]
+        action = "open_hero_menu"
+        port = 0
+
+        [[step]]
+        action = "choose_hero_spell"
+        port = 0
+        selection = 3
+
+        [[step]]
+        action = "wait"
+        frames = 5
+
+        [[step]]
+        action = "lose_stock"
+        port = 1
+        self_destruct = true
+
+        [[step]]
+        action = "results"
+    "#;
+
+    fn run(scenario: Scenario) -> (Info, Vec<Event>) {
+        let info = Info::new();
+        let mut deriver = EventDeriver::new();
+        let mut simulator = Simulator::new(scenario);
+        let mut events = Vec::new();
+        deriver.update(&info.snapshot());
+        while !simulator.is_finished() {
+            simulator.tick(&info);
+            events.extend(deriver.update(&info.snapshot()));
+        }
+        (info, events)
+    }
+
+    #[test]
+    fn test_parse() {
+        let scenario = Scenario::parse(SCENARIO).unwrap();
+        assert_eq!(scenario.name.as_deref(), Some("Hero ditto"));
+        assert_eq!(scenario.steps.len(), 12);
+        assert_eq!(scenario.steps[2], Step::Pick { port: 1, character: Character::Mario, skin: 2, name: None, cpu: true, team: None });
+        assert_eq!(scenario.frames(), 15);
+    }
+
+    #[test]
+    fn test_example() {
+        let scenario = Scenario::parse(include_str!("../scenarios/hero_ditto.toml")).unwrap();
+        let (_, events) = run(scenario);
+        assert_eq!(events.iter().filter(|event| matches!(event, Event::StockLost { .. })).count(), 3);
+        assert_eq!(events.last(), Some(&Event::ArenaLeft { arena_id: "ABCDE".to_owned() }));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(Scenario::parse("[[step]]\naction = \"jump\""), Err(ScenarioError::Parse(_))));
+        assert!(matches!(Scenario::parse("[[step]]\naction = \"wait\"\nframes = 1\nport = 0"), Err(ScenarioError::Parse(_))));
+        assert_eq!(
+            Scenario::parse("[[step]]\naction = \"wait\"\nframes = 1\n[[step]]\naction = \"damage\"\nport = 8\npercent = 1.0"),
+            Err(ScenarioError::Invalid("step 2: port must be 0 to 7".to_owned()))
+        );
+        assert!(matches!(Scenario::parse("[[step]]\naction = \"pick\"\nport = 0\ncharacter = \"bravo\""), Err(ScenarioError::Invalid(_))));
+        assert!(matches!(Scenario::parse("[[step]]\naction = \"join_arena\"\narena_id = \"ABC\""), Err(ScenarioError::Invalid(_))));
+        assert!(matches!(Scenario::parse("[[step]]\naction = \"results\"\nplacements = [0, 9]"), Err(ScenarioError::Invalid(_))));
+    }
+
+    #[test]
+    fn test_run() {
+        let (info, events) = run(Scenario::parse(SCENARIO).unwrap());
+        assert_eq!(events, [
+            Event::ArenaJoined { arena_id: "ABCDE".to_owned() },
+            Event::StageSelected { stage: Stage::BattleField },
+            Event::CharacterChanged { port: 0, character: Character::Brave, skin: 0 },
+            Event::CharacterChanged { port: 1, character: Character::Mario, skin: 2 },
+            Event::MatchStarted { stage: Stage::BattleField, ports: vec![0, 1] },
+            Event::HeroMenuOpened { port: 0 },
+            Event::HeroSpellChosen { port: 0, selection: 3 },
+            Event::MatchEnded,
+            Event::ResultsShown,
+            Event::MatchResult(MatchResult {
+                placements: vec![Placement { port: 0, placement: 1 }, Placement { port: 1, placement: 2 }],
+                winners: vec![0],
+                end: MatchEnd::Stocks,
+            }),
+        ]);
+
+        let players = &info.snapshot().players;
+        assert_eq!(players[0].name.as_deref(), Some("sharlot"));
+        assert_eq!(players[1].stocks, 1);
+        assert_eq!(players[1].self_destructs, 1);
+        assert_eq!(players[1].damage, 0.0);
+        assert!(info.is_results_screen() && !info.is_match());
+        assert_eq!(info.frame(), 15);
+    }
+
+    #[test]
+    fn test_stock_loss_during_match() {
+        let scenario = Scenario::parse(r#"
+            [[step]]
+            action = "pick"
+            port = 0
+            character = "mario"
+
+            [[step]]
+            action = "start_match"
+            stocks = 3
+            time_minutes = 1
+
+            [[step]]
+            action = "wait"
+            frames = 60
+
+            [[step]]
+            action = "lose_stock"
+            port = 0
+        "#).unwrap();
+
+        let (info, events) = run(scenario);
+        assert_eq!(events.last(), Some(&Event::StockLost { port: 0, stocks: 2 }));
+        assert_eq!(info.remaining_frames(), 59 * FRAMES_PER_SECOND - 1);
+        let clock = info.clock();
+        assert_eq!((clock.remaining_frames, clock.time_limit_frames), (Some(59 * FRAMES_PER_SECOND - 1), Some(60 * FRAMES_PER_SECOND)));
+        assert_eq!((clock.elapsed_frames, clock.mode), (FRAMES_PER_SECOND + 1, MatchMode::Stock));
+        // the match's stock count, not what's left
+        assert_eq!(info.ruleset(), Some(Ruleset { stocks: Some(3) }));
+    }
+
+    #[test]
+    fn test_stats() {
+        let scenario = Scenario::parse(r#"
+            [[step]]
+            action = "pick"
+            port = 0
+            character = "mario"
+
+            [[step]]
+            action = "pick"
+            port = 1
+            character = "link"
+
+            [[step]]
+            action = "start_match"
+            stocks = 2
+
+            [[step]]
+            action = "damage"
+            port = 1
+            percent = 40.0
+            by = 0
+
+            [[step]]
+            action = "wait"
+            frames = 30
+
+            [[step]]
+            action = "lose_stock"
+            port = 1
+            by = 0
+            ko = "top"
+
+            [[step]]
+            action = "wait"
+            frames = 60
+
+            [[step]]
+            action = "results"
+        "#).unwrap();
+
+        let (info, _) = run(scenario);
+        let (mario, link) = (info.players[0].stats(), info.players[1].stats());
+        assert_eq!((mario.damage_dealt, mario.kos, mario.average_ko_percent), (40.0, 1, Some(40.0)));
+        assert_eq!((link.damage_taken, link.falls), (40.0, 1));
+        assert_eq!((mario.longest_stock_frames, link.longest_stock_frames), (90, 60));
+        assert_eq!(info.stock_losses(), [
+            StockLoss { port: 1, frame: 30, percent: 40.0, killer: Some(0), ko: KoType::Top },
+        ]);
+        assert!(matches!(
+            Scenario::parse("[[step]]\naction = \"lose_stock\"\nport = 0\nby = 8"),
+            Err(ScenarioError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_sudden_death() {
+        let scenario = Scenario::parse(r#"
+            [[step]]
+            action = "pick"
+            port = 0
+            character = "mario"
+
+            [[step]]
+            action = "pick"
+            port = 1
+            character = "link"
+
+            [[step]]
+            action = "start_match"
+            time_minutes = 2
+            mode = "time"
+
+            [[step]]
+            action = "wait"
+            frames = 10
+
+            [[step]]
+            action = "sudden_death"
+
+            [[step]]
+            action = "wait"
+            frames = 10
+
+            [[step]]
+            action = "lose_stock"
+            port = 1
+            by = 0
+
+            [[step]]
+            action = "results"
+        "#).unwrap();
+
+        let (info, _) = run(scenario);
+        let clock = info.clock();
+        assert!(clock.sudden_death);
+        assert_eq!((clock.remaining_frames, clock.mode), (None, MatchMode::Time));
+        assert_eq!(info.match_end(), MatchEnd::SuddenDeath);
+        // nobody has stocks in a time battle; the KO decides it
+        assert_eq!(info.players[1].stocks(), 0);
+        assert_eq!((info.players[0].placement(), info.players[1].placement()), (1, 2));
+        assert_eq!(info.ruleset(), Some(Ruleset { stocks: None }));
+    }
+}
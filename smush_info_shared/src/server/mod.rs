@@ -0,0 +1,870 @@
+//! The plugin's server: pushes an [`Info`] to every client on the line, WebSocket and HTTP ports
+//! and answers their [`Command`]s. Sockets come from a [`Transport`], so the same server runs on
+//! the Switch and, against `std::net`, on a PC.
+use std::collections::VecDeque;
+use std::io;
+use std::net::SocketAddrV4;
+use std::sync::{Arc, Weak, Mutex, Condvar};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use serde::Serialize;
+
+use crate::{Info, IdFormat, with_id_format};
+use crate::config::ServerConfig;
+use crate::delta::DeltaEncoder;
+use crate::events::Event;
+use crate::handshake::{Hello, ClientHello, FieldFilter};
+use crate::http::{self, Request, Route, Status};
+use crate::protocol::{self, Command, Reply, ServerMessage, FrameBuffer, Encoding};
+use crate::websocket::{self, Opcode, FrameDecoder};
+
+mod transport;
+pub use transport::{Transport, Connection, StdTransport};
+
+// how long `run` waits before (re)starting a server, e.g. for the network to come up after boot
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+// events a session may have waiting to be sent; a client that falls this far behind is dropped
+const MAX_QUEUED_EVENTS: usize = 256;
+
+/// How messages are delimited on a connection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Framing {
+    /// Newline-delimited JSON, as on port 4242.
+    Lines,
+    /// HTTP upgrade followed by one text frame per message, for browsers.
+    WebSocket,
+    /// One plain HTTP request per connection, see [`http::Route`].
+    Http,
+}
+
+/// Everything the servers on each port share: the info they push, their settings and their
+/// clients.
+pub struct Server {
+    info: &'static Info,
+    config: ServerConfig,
+    hello: Box<dyn Fn() -> Hello + Send + Sync>,
+    client_count: AtomicUsize,
+    // every live session, for pushing events that don't wait on a client's send loop
+    sessions: Mutex<Vec<Weak<Session>>>,
+    // wakes frame-synced send loops whenever `info.frame` advances
+    frame_signal: (Mutex<()>, Condvar),
+}
+
+impl Server {
+    /// `hello` is called for every new client, so it can report hooks that resolve late.
+    pub fn new(info: &'static Info, config: ServerConfig, hello: impl Fn() -> Hello + Send + Sync + 'static) -> Self {
+        Self {
+            info,
+            config,
+            hello: Box::new(hello),
+            client_count: AtomicUsize::new(0),
+            sessions: Mutex::new(Vec::new()),
+            frame_signal: (Mutex::new(()), Condvar::new()),
+        }
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.client_count.load(Ordering::SeqCst)
+    }
+
+    /// Sends events to every client that isn't paused, as soon as they're derived. Each client's
+    /// own thread does the sending, so this never waits on a slow client.
+    pub fn broadcast_events(&self, events: &[Event]) {
+        if events.is_empty() {
+            return
+        }
+
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|session| session.strong_count() > 0);
+        for session in sessions.iter().filter_map(Weak::upgrade) {
+            if !session.is_closed() && !session.paused.load(Ordering::SeqCst) {
+                session.queue_events(events);
+            }
+        }
+    }
+
+    /// Wakes frame-synced clients. Call whenever `Info::frame` advances.
+    pub fn notify_frame(&self) {
+        let (lock, signal) = &self.frame_signal;
+        let _guard = lock.lock().unwrap();
+        signal.notify_all();
+    }
+
+    /// Blocks until `every` frames have passed since `after`, or `timeout` runs out (e.g. outside
+    /// a match, where frames don't advance). Returns the frame it woke on.
+    fn wait_for_frames(&self, after: u32, every: u32, timeout: Duration) -> u32 {
+        let (lock, signal) = &self.frame_signal;
+        let guard = lock.lock().unwrap();
+        let _ = signal
+            .wait_timeout_while(guard, timeout, |_| self.info.frame().wrapping_sub(after) < every)
+            .unwrap();
+        self.info.frame()
+    }
+
+    /// Serves `framing` on `port` forever, restarting after errors. Gives up only if the port is
+    /// taken, which usually means another copy of the server already has it.
+    pub fn run<T: Transport>(self: &Arc<Self>, port: u16, framing: Framing) -> io::Error {
+        loop {
+            std::thread::sleep(RESTART_DELAY);
+            match self.listen::<T>(port, framing) {
+                Err(err) if err.kind() == io::ErrorKind::AddrInUse => return err,
+                Err(err) => println!("[smush_info] server on port {} failed: {}", port, err),
+                Ok(()) => (),
+            }
+        }
+    }
+
+    /// Binds `port` on the configured address and serves it until accepting fails.
+    pub fn listen<T: Transport>(self: &Arc<Self>, port: u16, framing: Framing) -> io::Result<()> {
+        let addr = SocketAddrV4::new(self.config.listen_address, port);
+        self.serve(T::bind(addr, self.config.max_clients)?, framing)
+    }
+
+    /// Accepts clients on an already bound transport until accepting fails.
+    pub fn serve<T: Transport>(self: &Arc<Self>, mut transport: T, framing: Framing) -> io::Result<()> {
+        loop {
+            let connection = transport.accept()?;
+
+            // new connections past the limit are closed right after being accepted
+            if self.client_count() >= self.config.max_clients {
+                println!("Rejecting client, already serving {} clients", self.client_count());
+                continue
+            }
+
+            self.client_count.fetch_add(1, Ordering::SeqCst);
+            let session = Session::new(Arc::clone(self), Box::new(connection), framing);
+            std::thread::spawn(move || session.serve());
+        }
+    }
+}
+
+/// State shared between a client's send loop and its command reader. The socket is closed once
+/// both have let go of it.
+struct Session {
+    server: Arc<Server>,
+    connection: Box<dyn Connection>,
+    framing: Framing,
+    send_lock: Mutex<()>,
+    closed: AtomicBool,
+    paused: AtomicBool,
+    interval_ms: AtomicU64,
+    // 0 pushes every `interval_ms` instead
+    every_n_frames: AtomicU32,
+    delta: AtomicBool,
+    // set whenever diff mode is (re-)enabled so the next push is a full snapshot
+    delta_reset: AtomicBool,
+    slug_ids: AtomicBool,
+    message_pack: AtomicBool,
+    fields: Mutex<FieldFilter>,
+    // broadcast events waiting for `event_loop`
+    events: Mutex<VecDeque<Event>>,
+    events_queued: Condvar,
+}
+
+impl Session {
+    fn new(server: Arc<Server>, connection: Box<dyn Connection>, framing: Framing) -> Self {
+        let (interval_ms, every_n_frames) = (server.config.interval_ms, server.config.every_n_frames);
+        Self {
+            server,
+            connection,
+            framing,
+            send_lock: Mutex::new(()),
+            closed: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            interval_ms: AtomicU64::new(interval_ms),
+            every_n_frames: AtomicU32::new(every_n_frames),
+            delta: AtomicBool::new(false),
+            delta_reset: AtomicBool::new(false),
+            slug_ids: AtomicBool::new(false),
+            message_pack: AtomicBool::new(false),
+            fields: Mutex::new(FieldFilter::default()),
+            events: Mutex::new(VecDeque::new()),
+            events_queued: Condvar::new(),
+        }
+    }
+
+    // replies and pushes come from different threads, so whole frames are sent under a lock
+    fn send_raw(&self, bytes: &[u8]) -> io::Result<()> {
+        let _guard = self.send_lock.lock().unwrap();
+        self.connection.send(bytes)
+    }
+
+    /// Encodes and sends a message. Encoding under the lock keeps a change of encoding from
+    /// overtaking messages that were already encoded the old way.
+    fn send<T: Serialize>(&self, msg: &T) -> io::Result<()> {
+        let _guard = self.send_lock.lock().unwrap();
+        self.write(&self.encode(msg))
+    }
+
+    /// Sends one frame from [`protocol::encode_as`], reframed for the connection. The caller must
+    /// hold `send_lock`.
+    fn write(&self, frame: &[u8]) -> io::Result<()> {
+        let encoding = self.encoding();
+        match self.framing {
+            Framing::Lines => self.connection.send(frame),
+            Framing::WebSocket => {
+                let opcode = match encoding {
+                    Encoding::Json => Opcode::Text,
+                    Encoding::MessagePack => Opcode::Binary,
+                };
+                self.connection.send(&websocket::encode_frame(opcode, protocol::frame_payload(encoding, frame)))
+            }
+            Framing::Http => self.connection.send(&http::sse_event("message", frame)),
+        }
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> Option<usize> {
+        match self.connection.recv(buf) {
+            Ok(0) | Err(_) => None,
+            Ok(len) => Some(len),
+        }
+    }
+
+    fn close(&self) {
+        if !self.closed.swap(true, Ordering::SeqCst) {
+            self.connection.shutdown();
+        }
+        let _events = self.events.lock().unwrap();
+        self.events_queued.notify_all();
+    }
+
+    /// Hands events to [`event_loop`](Self::event_loop). A client that has stopped reading is
+    /// disconnected once too many pile up, rather than buffered for without end.
+    fn queue_events(&self, events: &[Event]) {
+        let mut queue = self.events.lock().unwrap();
+        if queue.len() + events.len() > MAX_QUEUED_EVENTS {
+            drop(queue);
+            println!("client fell {} events behind, dropping it", MAX_QUEUED_EVENTS);
+            self.close();
+            return
+        }
+        queue.extend(events.iter().cloned());
+        self.events_queued.notify_one();
+    }
+
+    /// Sends queued events as they come in, until the session closes.
+    fn event_loop(&self) {
+        loop {
+            let events: Vec<Event> = {
+                let queue = self.events.lock().unwrap();
+                let mut queue = self.events_queued
+                    .wait_while(queue, |queue| queue.is_empty() && !self.is_closed())
+                    .unwrap();
+                if self.is_closed() {
+                    return
+                }
+                queue.drain(..).collect()
+            };
+
+            for event in events {
+                let sent = match self.framing {
+                    Framing::Http => self.send_raw(&http::sse_event(event.name(), &self.encode(&event))),
+                    _ => self.send(&ServerMessage::Event { event }),
+                };
+                if sent.is_err() {
+                    self.close();
+                    return
+                }
+            }
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    fn id_format(&self) -> IdFormat {
+        if self.slug_ids.load(Ordering::SeqCst) {
+            IdFormat::Slug
+        } else {
+            IdFormat::Number
+        }
+    }
+
+    fn encoding(&self) -> Encoding {
+        if self.message_pack.load(Ordering::SeqCst) {
+            Encoding::MessagePack
+        } else {
+            Encoding::Json
+        }
+    }
+
+    /// Encodes a message with this client's choice of encoding, character/stage ids and fields.
+    fn encode<T: Serialize>(&self, msg: &T) -> Vec<u8> {
+        let encoding = self.encoding();
+        with_id_format(self.id_format(), || {
+            let fields = self.fields.lock().unwrap();
+            if fields.is_empty() {
+                protocol::encode_as(encoding, msg)
+            } else {
+                let mut value = serde_json::to_value(msg).unwrap();
+                fields.apply(&mut value);
+                protocol::encode_as(encoding, &value)
+            }
+        })
+    }
+
+    fn set_interval(&self, millis: u64) -> u64 {
+        let millis = millis.max(protocol::MIN_INTERVAL_MS);
+        self.interval_ms.store(millis, Ordering::SeqCst);
+        millis
+    }
+
+    fn set_delta(&self, enabled: bool) {
+        self.delta.store(enabled, Ordering::SeqCst);
+        self.delta_reset.store(true, Ordering::SeqCst);
+    }
+
+    fn handle_command(&self, command: Command) -> io::Result<()> {
+        let reply = match command {
+            Command::Hello(hello) => {
+                if let Some(format) = hello.id_format {
+                    self.slug_ids.store(format == IdFormat::Slug, Ordering::SeqCst);
+                }
+                if let Some(enabled) = hello.delta {
+                    self.set_delta(enabled);
+                }
+                if let Some(millis) = hello.interval_ms {
+                    self.set_interval(millis);
+                }
+                if let Some(every) = hello.every_n_frames {
+                    self.every_n_frames.store(every, Ordering::SeqCst);
+                }
+                if let Some(fields) = &hello.fields {
+                    *self.fields.lock().unwrap() = FieldFilter::new(fields);
+                    // the next push must be a full snapshot of the new field set
+                    self.delta_reset.store(true, Ordering::SeqCst);
+                }
+
+                let encoding = hello.encoding.unwrap_or_else(|| self.encoding());
+                let welcome = Reply::Welcome(ClientHello {
+                    id_format: Some(self.id_format()),
+                    delta: Some(self.delta.load(Ordering::SeqCst)),
+                    interval_ms: Some(self.interval_ms.load(Ordering::SeqCst)),
+                    every_n_frames: Some(self.every_n_frames.load(Ordering::SeqCst)),
+                    fields: hello.fields,
+                    encoding: Some(encoding),
+                });
+
+                // the welcome goes out the old way and everything after it the new way
+                let _guard = self.send_lock.lock().unwrap();
+                self.write(&self.encode(&welcome))?;
+                self.message_pack.store(encoding == Encoding::MessagePack, Ordering::SeqCst);
+                return Ok(())
+            }
+            Command::Ping => Reply::Pong,
+            Command::GetInfo => return self.send(&self.server.info.snapshot()),
+            Command::SetInterval { millis } => Reply::IntervalSet { millis: self.set_interval(millis) },
+            Command::Pause => {
+                self.paused.store(true, Ordering::SeqCst);
+                Reply::Paused
+            }
+            Command::Resume => {
+                self.paused.store(false, Ordering::SeqCst);
+                Reply::Resumed
+            }
+            Command::Version => Reply::Version { version: (self.server.hello)().plugin_version },
+            Command::SetDelta { enabled } => {
+                self.set_delta(enabled);
+                Reply::DeltaSet { enabled }
+            }
+            Command::SetIdFormat { format } => {
+                self.slug_ids.store(format == IdFormat::Slug, Ordering::SeqCst);
+                Reply::IdFormatSet { format }
+            }
+            Command::SetFrameSync { every_n_frames } => {
+                self.every_n_frames.store(every_n_frames, Ordering::SeqCst);
+                Reply::FrameSyncSet { every_n_frames }
+            }
+        };
+
+        self.send(&reply)
+    }
+
+    fn handle_frame(&self, frame: &[u8]) -> io::Result<()> {
+        match protocol::decode_command(frame) {
+            Ok(command) => self.handle_command(command),
+            Err(err) => self.send(&Reply::Error { message: format!("{:?}", err) }),
+        }
+    }
+
+    /// Reads an HTTP request head. Returns it along with whatever the client sent after it.
+    fn read_request(&self) -> Option<(Request, Vec<u8>)> {
+        let mut received = Vec::new();
+        let mut buf = [0u8; 0x200];
+
+        loop {
+            let len = self.recv(&mut buf)?;
+            received.extend_from_slice(&buf[..len]);
+
+            match http::parse_request(&received) {
+                Ok(None) => continue,
+                Ok(Some((request, head_len))) => return Some((request, received.split_off(head_len))),
+                Err(_) => {
+                    let _ = self.send_raw(&http::error_response(Status::BadRequest));
+                    return None
+                }
+            }
+        }
+    }
+
+    /// Reads the HTTP upgrade request and answers it. Returns whatever the client sent after it.
+    fn websocket_handshake(&self) -> Option<Vec<u8>> {
+        let (request, received) = self.read_request()?;
+        match websocket::upgrade_response(&request) {
+            Ok(response) => {
+                self.send_raw(&response).ok()?;
+                Some(received)
+            }
+            Err(_) => {
+                let _ = self.send_raw(&http::error_response(Status::BadRequest));
+                None
+            }
+        }
+    }
+
+    /// Runs one client's connection: the WebSocket upgrade if needed, then the hello, the command
+    /// reader and the send loop.
+    fn serve(self) {
+        let received = match self.framing {
+            Framing::Lines => Vec::new(),
+            Framing::WebSocket => match self.websocket_handshake() {
+                Some(received) => received,
+                None => return,
+            },
+            Framing::Http => return self.serve_http(),
+        };
+
+        let session = Arc::new(self);
+        if session.send(&ServerMessage::Hello { hello: (session.server.hello)() }).is_err() {
+            return
+        }
+
+        session.server.sessions.lock().unwrap().push(Arc::downgrade(&session));
+        let reader = Arc::clone(&session);
+        std::thread::spawn(move || reader.command_loop(received));
+        let events = Arc::clone(&session);
+        std::thread::spawn(move || events.event_loop());
+        session.client_loop();
+    }
+
+    /// Answers one request on the HTTP port. `/events` keeps the connection open as an event
+    /// stream until the client goes away.
+    fn serve_http(self) {
+        let request = match self.read_request() {
+            Some((request, _)) => request,
+            None => return,
+        };
+
+        if request.query("ids") == Some("slug") {
+            self.slug_ids.store(true, Ordering::SeqCst);
+        }
+
+        let info = self.server.info;
+        let body = match request.route(info.players.len()) {
+            Ok(Route::Info) => self.encode(&info.snapshot()),
+            Ok(Route::Player(port)) => self.encode(&info.players[port].snapshot()),
+            Ok(Route::Events) => return Arc::new(self).serve_event_stream(),
+            Err(status) => {
+                let _ = self.send_raw(&http::error_response(status));
+                return
+            }
+        };
+
+        let _ = self.send_raw(&http::response(Status::Ok, "application/json", &body));
+    }
+
+    fn serve_event_stream(self: Arc<Self>) {
+        let hello = self.encode(&(self.server.hello)());
+        if self.send_raw(&http::event_stream_head()).is_err()
+            || self.send_raw(&http::sse_event("hello", &hello)).is_err()
+        {
+            return
+        }
+
+        self.server.sessions.lock().unwrap().push(Arc::downgrade(&self));
+        let events = Arc::clone(&self);
+        std::thread::spawn(move || events.event_loop());
+
+        // events are written by the event loop; this only waits for the client to hang up
+        let mut buf = [0u8; 0x200];
+        while !self.is_closed() && self.recv(&mut buf).is_some() {}
+        self.close();
+    }
+
+    /// Reads commands off a client's socket until it hangs up.
+    fn command_loop(&self, received: Vec<u8>) {
+        let mut lines = FrameBuffer::new();
+        let mut frames = FrameDecoder::new();
+        let mut buf = [0u8; 0x200];
+        let mut data = received;
+
+        while !self.is_closed() {
+            let result = match self.framing {
+                Framing::Lines => {
+                    lines.extend(&data);
+                    self.read_lines(&mut lines)
+                }
+                Framing::WebSocket => {
+                    frames.extend(&data);
+                    self.read_websocket(&mut frames)
+                }
+                // event streams never carry commands
+                Framing::Http => break,
+            };
+
+            if result.is_err() {
+                break
+            }
+
+            match self.recv(&mut buf) {
+                Some(len) => data = buf[..len].to_vec(),
+                None => break,
+            }
+        }
+
+        self.close();
+    }
+
+    fn read_lines(&self, lines: &mut FrameBuffer) -> io::Result<()> {
+        while let Some(frame) = lines.next_frame() {
+            match frame {
+                Ok(frame) => self.handle_frame(&frame)?,
+                Err(err) => self.send(&Reply::Error { message: format!("{:?}", err) })?,
+            }
+        }
+        Ok(())
+    }
+
+    fn read_websocket(&self, frames: &mut FrameDecoder) -> io::Result<()> {
+        while let Some(message) = frames.next_message() {
+            let message = match message {
+                Ok(message) => message,
+                Err(err) => {
+                    println!("Closing websocket client: {:?}", err);
+                    let _ = self.send_raw(&websocket::encode_frame(Opcode::Close, &1002u16.to_be_bytes()));
+                    return Err(io::ErrorKind::InvalidData.into());
+                }
+            };
+
+            match message.opcode {
+                Opcode::Text | Opcode::Binary => self.handle_frame(&message.payload)?,
+                Opcode::Ping => self.send_raw(&websocket::encode_frame(Opcode::Pong, &message.payload))?,
+                Opcode::Close => {
+                    let _ = self.send_raw(&websocket::encode_frame(Opcode::Close, &message.payload));
+                    return Err(io::ErrorKind::ConnectionAborted.into());
+                }
+                Opcode::Pong | Opcode::Continuation => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Pushes the info to a single client until a send fails. Each client gets its own thread so
+    /// a dead or slow client never holds up the others.
+    fn client_loop(&self) {
+        let info = self.server.info;
+        let mut encoder = DeltaEncoder::new();
+        let mut frame = info.frame();
+
+        while !self.is_closed() {
+            if self.delta_reset.swap(false, Ordering::SeqCst) {
+                encoder.reset();
+            }
+
+            if !self.paused.load(Ordering::SeqCst) {
+                let snapshot = info.snapshot();
+                let sent = if self.delta.load(Ordering::SeqCst) {
+                    encoder.encode(&snapshot).map_or(Ok(()), |delta| self.send(&delta))
+                } else {
+                    self.send(&snapshot)
+                };
+
+                match sent {
+                    Ok(_) => (),
+                    Err(err) if err.kind() == io::ErrorKind::BrokenPipe => break, // client hung up
+                    Err(err) => {
+                        println!("send failed ({}), dropping client", err);
+                        break
+                    }
+                }
+            }
+
+            let interval = Duration::from_millis(self.interval_ms.load(Ordering::SeqCst));
+            match self.every_n_frames.load(Ordering::SeqCst) {
+                0 => std::thread::sleep(interval),
+                every => frame = self.server.wait_for_frames(frame, every, interval),
+            }
+        }
+
+        self.close();
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.server.client_count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod server_tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::io::{Read, Write};
+    use std::net::{Ipv4Addr, SocketAddr, TcpStream};
+    use crate::handshake::SCHEMA_VERSION;
+
+    fn hello() -> Hello {
+        Hello {
+            plugin_version: "0.0.0-test".to_owned(),
+            schema_version: SCHEMA_VERSION,
+            game_version: None,
+            hooks: BTreeMap::new(),
+        }
+    }
+
+    fn start(config: ServerConfig, framing: Framing) -> (Arc<Server>, SocketAddr) {
+        let info = Box::leak(Box::new(Info::new()));
+        let server = Arc::new(Server::new(info, config, hello));
+        let transport = StdTransport::bind(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0), 8).unwrap();
+        let addr = transport.local_addr().unwrap();
+        let serving = Arc::clone(&server);
+        std::thread::spawn(move || serving.serve(transport, framing));
+        (server, addr)
+    }
+
+    fn connect(addr: SocketAddr) -> TcpStream {
+        let stream = TcpStream::connect(addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        stream
+    }
+
+    struct LineClient {
+        stream: TcpStream,
+        frames: FrameBuffer,
+    }
+
+    impl LineClient {
+        fn connect(addr: SocketAddr) -> Self {
+            Self { stream: connect(addr), frames: FrameBuffer::new() }
+        }
+
+        fn send(&mut self, command: &str) {
+            self.stream.write_all(command.as_bytes()).unwrap();
+            self.stream.write_all(b"\n").unwrap();
+        }
+
+        fn next(&mut self) -> ServerMessage {
+            let mut buf = [0u8; 0x1000];
+            loop {
+                if let Some(frame) = self.frames.next_frame() {
+                    return protocol::decode_server_message(&frame.unwrap()).unwrap();
+                }
+                let len = self.stream.read(&mut buf).unwrap();
+                assert!(len > 0, "server hung up");
+                self.frames.extend(&buf[..len]);
+            }
+        }
+
+        /// The next message that isn't an info push.
+        fn next_other(&mut self) -> ServerMessage {
+            loop {
+                match self.next() {
+                    ServerMessage::Info(_) => continue,
+                    msg => return msg,
+                }
+            }
+        }
+
+        fn next_info(&mut self) -> crate::InfoSnapshot {
+            loop {
+                if let ServerMessage::Info(info) = self.next() {
+                    return *info;
+                }
+            }
+        }
+    }
+
+    fn fast_config() -> ServerConfig {
+        ServerConfig { interval_ms: protocol::MIN_INTERVAL_MS, ..Default::default() }
+    }
+
+    #[test]
+    fn test_lines() {
+        let (server, addr) = start(fast_config(), Framing::Lines);
+        let mut client = LineClient::connect(addr);
+        assert!(matches!(client.next(), ServerMessage::Hello { hello } if hello == self::hello()));
+
+        client.send("{\"command\":\"hello\",\"id_format\":\"slug\"}");
+        match client.next_other() {
+            ServerMessage::Reply(Reply::Welcome(welcome)) => assert_eq!(welcome.id_format, Some(IdFormat::Slug)),
+            msg => panic!("expected a welcome, got {:?}", msg),
+        }
+
+        client.send("{\"command\":\"ping\"}");
+        assert_eq!(client.next_other(), ServerMessage::Reply(Reply::Pong));
+        client.send("{\"command\":\"version\"}");
+        assert_eq!(client.next_other(), ServerMessage::Reply(Reply::Version { version: "0.0.0-test".to_owned() }));
+        client.send("nope");
+        assert!(matches!(client.next_other(), ServerMessage::Reply(Reply::Error { .. })));
+
+        server.info.stage.store(crate::Stage::BattleField as u32, Ordering::SeqCst);
+        while client.next_info().stage != crate::Stage::BattleField {}
+
+        server.broadcast_events(&[Event::MatchEnded]);
+        assert_eq!(client.next_other(), ServerMessage::Event { event: Event::MatchEnded });
+        assert_eq!(server.client_count(), 1);
+    }
+
+    #[test]
+    fn test_delta() {
+        let (server, addr) = start(fast_config(), Framing::Lines);
+        let mut client = LineClient::connect(addr);
+        client.next();
+        client.send("{\"command\":\"set_delta\",\"enabled\":true}");
+        assert_eq!(client.next_other(), ServerMessage::Reply(Reply::DeltaSet { enabled: true }));
+
+        let mut decoder = crate::delta::DeltaDecoder::new();
+        match client.next() {
+            ServerMessage::Delta(delta) => assert!(decoder.apply(delta).is_ok()),
+            msg => panic!("expected a full delta, got {:?}", msg),
+        }
+
+        server.info.players[3].stocks.store(2, Ordering::SeqCst);
+        loop {
+            if let ServerMessage::Delta(delta) = client.next() {
+                if decoder.apply(delta).unwrap().players[3].stocks == 2 {
+                    break
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_frame_sync() {
+        let config = ServerConfig { interval_ms: 60_000, every_n_frames: 3, ..Default::default() };
+        let (server, addr) = start(config, Framing::Lines);
+        let mut client = LineClient::connect(addr);
+        client.next();
+        assert_eq!(client.next_info().frame, 0);
+
+        for _ in 0..3 {
+            server.info.frame.fetch_add(1, Ordering::SeqCst);
+            server.notify_frame();
+        }
+        assert_eq!(client.next_info().frame, 3);
+    }
+
+    // a client that never takes anything off its socket
+    #[derive(Default)]
+    struct StalledConnection {
+        shut: Mutex<bool>,
+        signal: Condvar,
+    }
+
+    impl Connection for StalledConnection {
+        fn send(&self, _bytes: &[u8]) -> io::Result<()> {
+            let shut = self.shut.lock().unwrap();
+            let _shut = self.signal.wait_while(shut, |shut| !*shut).unwrap();
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn recv(&self, _buf: &mut [u8]) -> io::Result<usize> {
+            Ok(0)
+        }
+
+        fn shutdown(&self) {
+            *self.shut.lock().unwrap() = true;
+            self.signal.notify_all();
+        }
+    }
+
+    #[test]
+    fn test_stalled_client() {
+        let info = Box::leak(Box::new(Info::new()));
+        let server = Arc::new(Server::new(info, fast_config(), hello));
+        server.client_count.fetch_add(1, Ordering::SeqCst);
+        let session = Arc::new(Session::new(Arc::clone(&server), Box::<StalledConnection>::default(), Framing::Lines));
+        server.sessions.lock().unwrap().push(Arc::downgrade(&session));
+        let events = Arc::clone(&session);
+        let event_loop = std::thread::spawn(move || events.event_loop());
+
+        // broadcasting goes on while the client's own thread is stuck, until it's dropped
+        for _ in 0..2 * MAX_QUEUED_EVENTS + 2 {
+            server.broadcast_events(&[Event::MatchEnded]);
+        }
+        assert!(session.is_closed());
+        event_loop.join().unwrap();
+        drop(session);
+        assert_eq!(server.client_count(), 0);
+    }
+
+    #[test]
+    fn test_max_clients() {
+        let (server, addr) = start(ServerConfig { max_clients: 1, ..fast_config() }, Framing::Lines);
+        let mut first = LineClient::connect(addr);
+        first.next();
+
+        let mut second = connect(addr);
+        assert_eq!(second.read(&mut [0u8; 0x100]).unwrap(), 0);
+        assert_eq!(server.client_count(), 1);
+
+        drop(first);
+        let mut third = connect(addr);
+        let mut buf = [0u8; 0x100];
+        // the first client's session only goes away once its send loop notices
+        while third.read(&mut buf).unwrap() == 0 {
+            third = connect(addr);
+        }
+        assert!(buf.starts_with(b"{\"hello\""));
+    }
+
+    fn http_get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = connect(addr);
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_http() {
+        let (server, addr) = start(fast_config(), Framing::Http);
+        server.info.players[1].character.store(crate::Character::Brave as u32, Ordering::SeqCst);
+
+        let response = http_get(addr, "/info?ids=slug");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+        let info: crate::InfoSnapshot = serde_json::from_str(body.trim()).unwrap();
+        assert_eq!(info.players[1].character, crate::Character::Brave);
+        assert!(body.contains("\"brave\""));
+
+        assert!(http_get(addr, "/players/9").starts_with("HTTP/1.1 404"));
+    }
+
+    #[test]
+    fn test_websocket() {
+        let (_server, addr) = start(fast_config(), Framing::WebSocket);
+        let mut stream = connect(addr);
+        write!(
+            stream,
+            "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+        ).unwrap();
+
+        let mut received = Vec::new();
+        let mut buf = [0u8; 0x1000];
+        while !received.windows(8).any(|w| w == b"{\"hello\"") {
+            let len = stream.read(&mut buf).unwrap();
+            assert!(len > 0);
+            received.extend_from_slice(&buf[..len]);
+        }
+
+        let head_len = received.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        assert!(received.starts_with(b"HTTP/1.1 101"));
+        // a single unmasked text frame
+        assert_eq!(received[head_len], 0x81);
+    }
+}
@@ -0,0 +1,60 @@
+//! The sockets underneath a [`Server`](super::Server): libc on the Switch, `std::net` anywhere else.
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, SocketAddrV4, TcpListener, TcpStream};
+
+/// A listening socket that hands out [`Connection`]s.
+pub trait Transport: Sized + Send + 'static {
+    type Connection: Connection + 'static;
+
+    /// `backlog` is a hint; implementations that can't set it may ignore it.
+    fn bind(addr: SocketAddrV4, backlog: usize) -> io::Result<Self>;
+
+    fn accept(&mut self) -> io::Result<Self::Connection>;
+}
+
+/// One accepted client. Shared between a client's send loop and its reader, so everything takes
+/// `&self`. Dropping it closes the socket.
+pub trait Connection: Send + Sync {
+    /// Sends all of `bytes`.
+    fn send(&self, bytes: &[u8]) -> io::Result<()>;
+
+    /// `Ok(0)` once the client has hung up.
+    fn recv(&self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Wakes up a blocked `recv` and fails further sends, without closing the socket yet.
+    fn shutdown(&self);
+}
+
+pub struct StdTransport(TcpListener);
+
+impl StdTransport {
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.0.local_addr()
+    }
+}
+
+impl Transport for StdTransport {
+    type Connection = TcpStream;
+
+    fn bind(addr: SocketAddrV4, _backlog: usize) -> io::Result<Self> {
+        TcpListener::bind(addr).map(StdTransport)
+    }
+
+    fn accept(&mut self) -> io::Result<TcpStream> {
+        self.0.accept().map(|(stream, _)| stream)
+    }
+}
+
+impl Connection for TcpStream {
+    fn send(&self, bytes: &[u8]) -> io::Result<()> {
+        (&*self).write_all(bytes)
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        (&*self).read(buf)
+    }
+
+    fn shutdown(&self) {
+        let _ = TcpStream::shutdown(self, Shutdown::Both);
+    }
+}
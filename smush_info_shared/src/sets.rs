@@ -0,0 +1,418 @@
+//! Best-of sets, pieced together from the games in successive snapshots.
+//!
+//! A game goes to the match's winner on the results screen. Without a result, it goes to whoever
+//! has the most stocks left when the match ends, with lower damage breaking ties. A game nobody
+//! won outright isn't counted. A match with a different lineup, or any match after the set is
+//! decided, starts a new set. Players are told apart by port and name tag, or by character when
+//! they have no tag, so untagged players counterpicking also start a new set. In team battles
+//! each team plays as one side: teammates win games together, and changing teams starts a new set.
+use serde::{Serialize, Deserialize};
+
+use crate::{InfoSnapshot, PlayerSnapshot, Character};
+
+/// Set length until the tracker is told otherwise.
+pub const DEFAULT_BEST_OF: u32 = 3;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entrant {
+    pub port: usize,
+    pub name: Option<String>,
+    /// As of the latest game.
+    pub character: Character,
+    /// Team battles only.
+    pub team: Option<u32>,
+    /// Shared with teammates.
+    pub wins: u32,
+}
+
+impl Entrant {
+    fn new(port: usize, player: &PlayerSnapshot) -> Self {
+        Self { port, name: player.name.clone(), character: player.character, team: player.team, wins: 0 }
+    }
+
+    fn is(&self, port: usize, player: &PlayerSnapshot) -> bool {
+        self.port == port && self.team == player.team && match (&self.name, &player.name) {
+            (None, None) => self.character == player.character,
+            (name, other) => name == other,
+        }
+    }
+
+    /// The entrant's team, or the entrant alone outside of team battles.
+    fn side(&self) -> Result<u32, usize> {
+        self.team.ok_or(self.port)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SetState {
+    pub best_of: u32,
+    /// The game being played, or up next, counting from 1. Stays on the last game once the set
+    /// is over.
+    pub game: u32,
+    pub entrants: Vec<Entrant>,
+    /// Port of whoever took the set, or of the first player on the team that did.
+    pub winner: Option<usize>,
+}
+
+impl SetState {
+    fn new(best_of: u32, entrants: Vec<Entrant>) -> Self {
+        Self { best_of, game: 1, entrants, winner: None }
+    }
+
+    pub fn wins_needed(&self) -> u32 {
+        self.best_of / 2 + 1
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.winner.is_some()
+    }
+
+    fn lineup_matches(&self, next: &InfoSnapshot) -> bool {
+        let ports = in_game(next);
+        ports.len() == self.entrants.len()
+            && self.entrants.iter().zip(ports).all(|(entrant, (port, player))| entrant.is(port, player))
+    }
+}
+
+fn in_game(info: &InfoSnapshot) -> Vec<(usize, &PlayerSnapshot)> {
+    info.players.iter().enumerate().filter(|(_, player)| player.is_in_game).collect()
+}
+
+/// Turns a stream of snapshots into the state of the set being played.
+#[derive(Default)]
+pub struct SetTracker {
+    best_of: Option<u32>,
+    set: Option<SetState>,
+    prev: Option<InfoSnapshot>,
+}
+
+impl SetTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fixes the set length instead of assuming [`DEFAULT_BEST_OF`], starting with the current
+    /// set. Lengthening a decided set reopens it. `None` goes back to the default.
+    pub fn set_best_of(&mut self, best_of: Option<u32>) {
+        self.best_of = best_of;
+        if let (Some(best_of), Some(set)) = (best_of, &mut self.set) {
+            let was_over = set.is_over();
+            set.best_of = best_of;
+            let wins_needed = set.wins_needed();
+            set.winner = set.entrants.iter()
+                .find(|entrant| entrant.wins >= wins_needed)
+                .map(|entrant| entrant.port);
+            // the last game is done, so a reopened set is on to the next one
+            if was_over && !set.is_over() {
+                set.game += 1;
+            }
+        }
+    }
+
+    /// The current set, or the last one until the next match starts.
+    pub fn state(&self) -> Option<&SetState> {
+        self.set.as_ref()
+    }
+
+    /// Returns whether the set state changed.
+    pub fn update(&mut self, next: &InfoSnapshot) -> bool {
+        let changed = match self.prev.take() {
+            Some(prev) if !prev.is_match && next.is_match => {
+                self.start_game(next);
+                true
+            }
+            Some(prev) if prev.is_match && !next.is_match => self.end_game(&prev, next),
+            _ => false,
+        };
+        self.prev = Some(next.clone());
+        changed
+    }
+
+    fn start_game(&mut self, next: &InfoSnapshot) {
+        match &mut self.set {
+            Some(set) if set.lineup_matches(next) && !set.is_over() => {
+                for (entrant, (_, player)) in set.entrants.iter_mut().zip(in_game(next)) {
+                    entrant.character = player.character;
+                }
+            }
+            // the same players after a decided set are a runback, or their next set in the bracket
+            _ => {
+                let entrants = in_game(next).into_iter()
+                    .map(|(port, player)| Entrant::new(port, player))
+                    .collect();
+                self.set = Some(SetState::new(self.best_of.unwrap_or(DEFAULT_BEST_OF), entrants));
+            }
+        }
+    }
+
+    // `last` is the last snapshot of the match, before the plugin clears who was in game
+    fn end_game(&mut self, last: &InfoSnapshot, next: &InfoSnapshot) -> bool {
+        let set = match &mut self.set {
+            Some(set) if !set.is_over() => set,
+            _ => return false,
+        };
+
+        let side = match next.match_result() {
+            // teammates win together, so every winner has to be on the same side
+            Some(result) => only(result.winners.iter().map(|&port| {
+                set.entrants.iter().find(|entrant| entrant.port == port).map(Entrant::side)
+            })).flatten(),
+            None => Self::leader(set, last),
+        };
+        let side = match side {
+            Some(side) => side,
+            None => return false,
+        };
+
+        let wins_needed = set.wins_needed();
+        let mut winners = set.entrants.iter_mut().filter(|entrant| entrant.side() == side).peekable();
+        let winner = winners.peek().map(|entrant| entrant.port);
+        let mut wins = 0;
+        for entrant in winners {
+            entrant.wins += 1;
+            wins = entrant.wins;
+        }
+        if wins >= wins_needed {
+            set.winner = winner;
+        } else {
+            set.game += 1;
+        }
+        true
+    }
+
+    // the only side with the most stocks, or lowest damage among those
+    fn leader(set: &SetState, last: &InfoSnapshot) -> Option<Result<u32, usize>> {
+        let mut sides: Vec<(Result<u32, usize>, (u32, f32))> = Vec::new();
+        for entrant in &set.entrants {
+            let player = &last.players[entrant.port];
+            match sides.iter_mut().find(|(side, _)| *side == entrant.side()) {
+                Some((_, (stocks, damage))) => {
+                    *stocks += player.stocks;
+                    *damage -= player.damage;
+                }
+                None => sides.push((entrant.side(), (player.stocks, -player.damage))),
+            }
+        }
+        let best = sides.iter().map(|(_, standing)| *standing).reduce(|a, b| if b > a { b } else { a })?;
+        only(sides.into_iter().filter(|(_, standing)| *standing == best).map(|(side, _)| side))
+    }
+}
+
+// whatever every item is, if there are any and they're all the same
+fn only<T: PartialEq>(mut items: impl Iterator<Item = T>) -> Option<T> {
+    let first = items.next()?;
+    if items.all(|item| item == first) { Some(first) } else { None }
+}
+
+#[cfg(test)]
+mod sets_tests {
+    use super::*;
+    use crate::results::MatchEnd;
+
+    fn lineup() -> InfoSnapshot {
+        let mut info = InfoSnapshot::default();
+        for (port, (name, character)) in [("Alice", Character::Brave), ("Bob", Character::Mario)].iter().enumerate() {
+            info.players[port].name = Some(name.to_string());
+            info.players[port].character = *character;
+        }
+        info
+    }
+
+    // plays a match on `info` where `winner` takes the other player's last stock
+    fn play(tracker: &mut SetTracker, info: &mut InfoSnapshot, winner: usize) {
+        info.is_match = true;
+        for player in &mut info.players[..2] {
+            player.is_in_game = true;
+            player.stocks = 3;
+        }
+        tracker.update(info);
+
+        info.players[1 - winner].stocks = 0;
+        tracker.update(info);
+
+        info.is_match = false;
+        for player in &mut info.players[..2] {
+            player.is_in_game = false;
+        }
+        tracker.update(info);
+    }
+
+    fn score(tracker: &SetTracker) -> Vec<u32> {
+        tracker.state().unwrap().entrants.iter().map(|entrant| entrant.wins).collect()
+    }
+
+    #[test]
+    fn test_best_of_3() {
+        let mut tracker = SetTracker::new();
+        let mut info = lineup();
+        tracker.update(&info);
+        assert!(tracker.state().is_none());
+
+        play(&mut tracker, &mut info, 0);
+        let set = tracker.state().unwrap();
+        assert_eq!((set.best_of, set.game, set.winner), (3, 2, None));
+        assert_eq!(score(&tracker), [1, 0]);
+
+        // counterpicks stay in the same set
+        info.players[1].character = Character::Link;
+        play(&mut tracker, &mut info, 1);
+        assert_eq!(tracker.state().unwrap().entrants[1].character, Character::Link);
+        assert_eq!(score(&tracker), [1, 1]);
+
+        play(&mut tracker, &mut info, 1);
+        let set = tracker.state().unwrap();
+        assert_eq!((set.game, set.winner), (3, Some(1)));
+    }
+
+    #[test]
+    fn test_runback() {
+        let mut tracker = SetTracker::new();
+        let mut info = lineup();
+        tracker.update(&info);
+        play(&mut tracker, &mut info, 0);
+        play(&mut tracker, &mut info, 0);
+        assert_eq!(tracker.state().unwrap().winner, Some(0));
+
+        // the same players playing on start their next set
+        play(&mut tracker, &mut info, 1);
+        let set = tracker.state().unwrap();
+        assert_eq!((set.best_of, set.game, set.winner), (3, 2, None));
+        assert_eq!(score(&tracker), [0, 1]);
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut tracker = SetTracker::new();
+        let mut info = lineup();
+        tracker.update(&info);
+        play(&mut tracker, &mut info, 0);
+        play(&mut tracker, &mut info, 0);
+
+        // it was a best of 5 after all
+        tracker.set_best_of(Some(5));
+        assert_eq!(tracker.state().unwrap().winner, None);
+        play(&mut tracker, &mut info, 1);
+        let set = tracker.state().unwrap();
+        assert_eq!((set.best_of, set.game, set.winner), (5, 4, None));
+        assert_eq!(score(&tracker), [2, 1]);
+    }
+
+    #[test]
+    fn test_new_lineup() {
+        let mut tracker = SetTracker::new();
+        let mut info = lineup();
+        tracker.update(&info);
+        play(&mut tracker, &mut info, 0);
+
+        info.players[1].name = Some("Carol".to_owned());
+        play(&mut tracker, &mut info, 0);
+        let set = tracker.state().unwrap();
+        assert_eq!((set.game, set.entrants[1].name.as_deref()), (2, Some("Carol")));
+        assert_eq!(score(&tracker), [1, 0]);
+
+        // untagged players are told apart by character
+        info.players[1].name = None;
+        play(&mut tracker, &mut info, 0);
+        info.players[1].character = Character::Link;
+        play(&mut tracker, &mut info, 0);
+        assert_eq!(score(&tracker), [1, 0]);
+    }
+
+    #[test]
+    fn test_best_of_override() {
+        let mut tracker = SetTracker::new();
+        let mut info = lineup();
+        tracker.update(&info);
+        tracker.set_best_of(Some(5));
+        play(&mut tracker, &mut info, 0);
+        play(&mut tracker, &mut info, 0);
+        assert_eq!(tracker.state().unwrap().winner, None);
+        play(&mut tracker, &mut info, 0);
+        assert_eq!(tracker.state().unwrap().winner, Some(0));
+
+        // a fixed length isn't stretched; the same players start over
+        play(&mut tracker, &mut info, 1);
+        assert_eq!(score(&tracker), [0, 1]);
+
+        // shortening decides the set right away
+        play(&mut tracker, &mut info, 1);
+        tracker.set_best_of(Some(3));
+        assert_eq!(tracker.state().unwrap().winner, Some(1));
+    }
+
+    #[test]
+    fn test_doubles() {
+        let mut tracker = SetTracker::new();
+        let mut info = InfoSnapshot::default();
+        for (port, name) in ["Alice", "Bob", "Carol", "Dan"].iter().enumerate() {
+            info.players[port].name = Some(name.to_string());
+            info.players[port].team = Some(port as u32 / 2);
+        }
+        tracker.update(&info);
+
+        // the game places both winners first
+        let mut play = |info: &mut InfoSnapshot, team: u32| {
+            info.is_match = true;
+            for player in &mut info.players[..4] {
+                player.is_in_game = true;
+            }
+            tracker.update(info);
+            info.is_match = false;
+            info.match_end = MatchEnd::Stocks;
+            for player in &mut info.players[..4] {
+                player.is_in_game = false;
+                player.placement = if player.team == Some(team) { 1 } else { 2 };
+            }
+            tracker.update(info);
+            tracker.state().cloned().unwrap()
+        };
+        let set = play(&mut info, 1);
+        assert_eq!(set.entrants.iter().map(|entrant| entrant.wins).collect::<Vec<_>>(), [0, 0, 1, 1]);
+        play(&mut info, 0);
+        let set = play(&mut info, 1);
+        assert_eq!((set.game, set.winner), (3, Some(2)));
+
+        // swapping partners is a new set
+        info.players[1].team = Some(1);
+        info.players[2].team = Some(0);
+        let set = play(&mut info, 0);
+        assert_eq!((set.game, set.winner), (2, None));
+        assert_eq!(set.entrants.iter().map(|entrant| entrant.wins).collect::<Vec<_>>(), [1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn test_ties_arent_counted() {
+        let mut tracker = SetTracker::new();
+        let mut info = lineup();
+        tracker.update(&info);
+        info.is_match = true;
+        for player in &mut info.players[..2] {
+            player.is_in_game = true;
+            player.stocks = 2;
+            player.damage = 50.0;
+        }
+        tracker.update(&info);
+        info.is_match = false;
+        assert!(!tracker.update(&info));
+        assert_eq!(tracker.state().unwrap().game, 1);
+
+        // on time, lower damage takes it
+        info.is_match = true;
+        tracker.update(&info);
+        info.players[0].damage = 20.0;
+        tracker.update(&info);
+        info.is_match = false;
+        assert!(tracker.update(&info));
+        assert_eq!(score(&tracker), [1, 0]);
+
+        // the results screen knows better
+        info.is_match = true;
+        tracker.update(&info);
+        info.is_match = false;
+        info.match_end = MatchEnd::Time;
+        info.players[1].placement = 1;
+        info.players[0].placement = 2;
+        assert!(tracker.update(&info));
+        assert_eq!(score(&tracker), [1, 1]);
+    }
+}
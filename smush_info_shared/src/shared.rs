@@ -0,0 +1,87 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+use core::fmt;
+
+/// A value too big for an atomic, behind a lock. Serializes as the value itself.
+pub struct Shared<T>(Mutex<T>);
+
+impl<T> Shared<T> {
+    pub const fn new(value: T) -> Self {
+        Self(Mutex::new(value))
+    }
+
+    pub fn store(&self, value: T) {
+        *self.lock() = value;
+    }
+
+    /// Changes the value in place, without anyone seeing it half done.
+    pub fn modify<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.lock())
+    }
+
+    // a panic elsewhere can't leave the value half written, so a poisoned lock is still good
+    fn lock(&self) -> MutexGuard<'_, T> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Clone> Shared<T> {
+    pub fn load(&self) -> T {
+        self.lock().clone()
+    }
+}
+
+impl<T: Default> Default for Shared<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.lock(), f)
+    }
+}
+
+impl<T: Serialize> Serialize for Shared<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer,
+    {
+        self.lock().serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Shared<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        Ok(Shared::new(T::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod shared_tests {
+    use super::*;
+
+    #[test]
+    fn test_serde_round_trip() {
+        let x = Shared::new(vec![Some(1), None]);
+        let json = serde_json::to_string(&x).unwrap();
+        assert_eq!(json, "[1,null]");
+        let y: Shared<Vec<Option<u32>>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(x.load(), y.load());
+    }
+
+    #[test]
+    fn test_modify() {
+        let x = Shared::new(vec![1]);
+        let len = x.modify(|x| {
+            x.push(2);
+            x.len()
+        });
+        assert_eq!((len, x.load()), (2, vec![1, 2]));
+        x.store(Vec::new());
+        assert_eq!(format!("{:?}", x), "[]");
+    }
+}
@@ -0,0 +1,121 @@
+use smush_info_shared::Info;
+use smush_info_shared::config::ServerConfig;
+use smush_info_shared::events::EventDeriver;
+use smush_info_shared::handshake::{Hello, SCHEMA_VERSION};
+use smush_info_shared::scenario::{Scenario, Simulator, FRAMES_PER_SECOND};
+use smush_info_shared::server::{Server, Framing, Transport, StdTransport};
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: smush_info_simulate <scenario.toml> [--speed <multiplier>] [--port <port>]";
+const CLIENT_POLL: Duration = Duration::from_millis(50);
+
+// stands in for the plugin's `GAME_INFO`
+static INFO: Info = Info::new();
+
+struct Options {
+    path: String,
+    speed: f64,
+    port: u16,
+}
+
+fn parse_args() -> Option<Options> {
+    let mut options = Options { path: String::new(), speed: 1.0, port: ServerConfig::default().port };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--speed" => match args.next()?.parse() {
+                Ok(speed) if speed > 0.0 => options.speed = speed,
+                _ => return None,
+            },
+            "--port" => options.port = args.next()?.parse().ok()?,
+            _ if options.path.is_empty() && !arg.starts_with("--") => options.path = arg,
+            _ => return None,
+        }
+    }
+    if options.path.is_empty() {
+        None
+    } else {
+        Some(options)
+    }
+}
+
+fn hello() -> Hello {
+    Hello {
+        plugin_version: env!("CARGO_PKG_VERSION").to_owned(),
+        schema_version: SCHEMA_VERSION,
+        game_version: None,
+        hooks: BTreeMap::new(),
+    }
+}
+
+fn main() {
+    let options = match parse_args() {
+        Some(options) => options,
+        None => {
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    let scenario = match Scenario::load(&options.path) {
+        Ok(scenario) => scenario,
+        Err(err) => {
+            eprintln!("{}: {}", options.path, err);
+            std::process::exit(1);
+        }
+    };
+
+    let config = ServerConfig { port: options.port, ..Default::default() };
+    let transport = match StdTransport::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, options.port), config.max_clients) {
+        Ok(transport) => transport,
+        Err(err) => {
+            eprintln!("Couldn't listen on port {}: {}", options.port, err);
+            std::process::exit(1);
+        }
+    };
+
+    let server = Arc::new(Server::new(&INFO, config, hello));
+    {
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || server.serve(transport, Framing::Lines));
+    }
+
+    println!("Waiting for a client on port {}", options.port);
+    while server.client_count() == 0 {
+        std::thread::sleep(CLIENT_POLL);
+    }
+
+    let name = scenario.name.clone().unwrap_or_else(|| options.path.clone());
+    println!("Running {} ({} frames)", name, scenario.frames());
+
+    let frame_time = Duration::from_secs(1).div_f64(FRAMES_PER_SECOND as f64 * options.speed);
+    let mut simulator = Simulator::new(scenario);
+    let mut deriver = EventDeriver::new();
+    deriver.update(&INFO.snapshot());
+    let mut next_frame = Instant::now();
+    let mut ticks = 0u64;
+    while !simulator.is_finished() {
+        simulator.tick(&INFO);
+        ticks += 1;
+        let events = deriver.update(&INFO.snapshot());
+        for event in &events {
+            println!("{:>6} {}", ticks, event.name());
+        }
+        server.broadcast_events(&events);
+        server.notify_frame();
+
+        next_frame += frame_time;
+        if let Some(wait) = next_frame.checked_duration_since(Instant::now()) {
+            std::thread::sleep(wait);
+        }
+    }
+
+    // keep serving the final state, e.g. the results screen, until stopped
+    println!("Scenario finished, press Ctrl-C to stop");
+    loop {
+        std::thread::park();
+    }
+}
@@ -0,0 +1,173 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{Info, Player, Character, Stage};
+use crate::clock::Clock;
+use crate::results::MatchEnd;
+use crate::ruleset::Ruleset;
+use crate::stats::Stats;
+use crate::timeline::StockLoss;
+
+// how many times `Info::snapshot` re-reads while the plugin is writing before settling
+const SNAPSHOT_RETRIES: usize = 4;
+
+/// Plain copy of an [`Info`]. Serializes to the same JSON, so clients can deserialize the info
+/// stream straight into this.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InfoSnapshot {
+    pub arena_id: Option<String>,
+    pub remaining_frames: u32,
+    pub current_menu: u32,
+    pub is_match: bool,
+    pub is_results_screen: bool,
+    pub stage: Stage,
+    #[serde(default)]
+    pub frame: u32,
+    #[serde(default)]
+    pub match_end: MatchEnd,
+    #[serde(default)]
+    pub stock_losses: Vec<StockLoss>,
+    #[serde(default)]
+    pub friendly_fire: bool,
+    #[serde(default)]
+    pub clock: Clock,
+    #[serde(default)]
+    pub ruleset: Option<Ruleset>,
+    pub players: [PlayerSnapshot; 8]
+}
+
+/// Plain copy of a [`Player`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PlayerSnapshot {
+    pub is_in_game: bool,
+    pub name: Option<String>,
+    pub character: Character,
+    pub stocks: u32,
+    pub self_destructs: u32,
+    pub damage: f32,
+    pub is_cpu: bool,
+    pub skin: u32,
+    pub x: f32,
+    pub y: f32,
+    pub hero_menu_open: bool,
+    pub hero_menu_selected: bool,
+    pub hero_menu_selection: u32,
+    #[serde(default)]
+    pub placement: u32,
+    #[serde(default)]
+    pub stats: Stats,
+    #[serde(default)]
+    pub team: Option<u32>
+}
+
+impl Info {
+    /// Copies every field out. The hooks may be writing while this runs, so it reads until two
+    /// passes agree (giving up after a few tries, since positions change every frame).
+    pub fn snapshot(&self) -> InfoSnapshot {
+        let mut snapshot = self.load_snapshot();
+        for _ in 0..SNAPSHOT_RETRIES {
+            let next = self.load_snapshot();
+            if next == snapshot {
+                break
+            }
+            snapshot = next;
+        }
+        snapshot
+    }
+
+    fn load_snapshot(&self) -> InfoSnapshot {
+        InfoSnapshot {
+            arena_id: self.arena_id(),
+            remaining_frames: self.remaining_frames(),
+            current_menu: self.current_menu(),
+            is_match: self.is_match(),
+            is_results_screen: self.is_results_screen(),
+            stage: self.stage(),
+            frame: self.frame(),
+            match_end: self.match_end(),
+            stock_losses: self.stock_losses(),
+            friendly_fire: self.friendly_fire(),
+            clock: self.clock(),
+            ruleset: self.ruleset(),
+            players: [
+                self.players[0].snapshot(),
+                self.players[1].snapshot(),
+                self.players[2].snapshot(),
+                self.players[3].snapshot(),
+                self.players[4].snapshot(),
+                self.players[5].snapshot(),
+                self.players[6].snapshot(),
+                self.players[7].snapshot(),
+            ]
+        }
+    }
+}
+
+impl Player {
+    pub fn snapshot(&self) -> PlayerSnapshot {
+        PlayerSnapshot {
+            is_in_game: self.is_in_game(),
+            name: self.name(),
+            character: self.character(),
+            stocks: self.stocks(),
+            self_destructs: self.self_destructs(),
+            damage: self.damage(),
+            is_cpu: self.is_cpu(),
+            skin: self.skin(),
+            x: self.x(),
+            y: self.y(),
+            hero_menu_open: self.hero_menu_open(),
+            hero_menu_selected: self.hero_menu_selected(),
+            hero_menu_selection: self.hero_menu_selection(),
+            placement: self.placement(),
+            stats: self.stats(),
+            team: self.team()
+        }
+    }
+}
+
+impl Default for InfoSnapshot {
+    fn default() -> Self {
+        Info::new().snapshot()
+    }
+}
+
+impl Default for PlayerSnapshot {
+    fn default() -> Self {
+        Player::new().snapshot()
+    }
+}
+
+#[cfg(test)]
+mod snapshot_tests {
+    use super::*;
+    use core::sync::atomic::Ordering;
+
+    #[test]
+    fn test_snapshot_fields() {
+        let info = Info::new();
+        info.stage.store(Stage::Battle_DK_Jungle as u32, Ordering::SeqCst);
+        info.players[1].character.store(Character::Brave as u32, Ordering::SeqCst);
+        info.players[1].name.store_str(Some("sharlot"), Ordering::SeqCst);
+        info.players[1].x.store(12.5, Ordering::SeqCst);
+
+        let snapshot = info.snapshot();
+        assert_eq!(snapshot.stage, Stage::Battle_DK_Jungle);
+        assert_eq!(snapshot.players[1].character, Character::Brave);
+        assert_eq!(snapshot.players[1].name.as_deref(), Some("sharlot"));
+        assert_eq!(snapshot.players[1].x, 12.5);
+        assert_eq!(snapshot.players[0], PlayerSnapshot::default());
+    }
+
+    #[test]
+    fn test_same_json_as_info() {
+        let info = Info::new();
+        info.arena_id.store_str(Some("ABCDE"), Ordering::SeqCst);
+        info.players[0].damage.store(42.0, Ordering::SeqCst);
+
+        let json = serde_json::to_string(&info).unwrap();
+        assert_eq!(serde_json::to_string(&info.snapshot()).unwrap(), json);
+
+        let snapshot: InfoSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot, info.snapshot());
+    }
+}
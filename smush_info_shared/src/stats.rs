@@ -0,0 +1,154 @@
+//! Per-player totals for the current match, kept up by the damage and death hooks.
+use serde::{Serialize, Deserialize};
+
+use crate::{Info, Player};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub damage_dealt: f32,
+    pub damage_taken: f32,
+    pub kos: u32,
+    pub falls: u32,
+    /// Mean percent of the players this one KO'd. `None` before the first KO.
+    pub average_ko_percent: Option<f32>,
+    /// In game frames. Stocks still in play count once they end, or when the match does.
+    pub longest_stock_frames: u32,
+}
+
+impl Stats {
+    pub const fn new() -> Self {
+        Self {
+            damage_dealt: 0.,
+            damage_taken: 0.,
+            kos: 0,
+            falls: 0,
+            average_ko_percent: None,
+            longest_stock_frames: 0,
+        }
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn end_stock(player: &Player, frame: u32) {
+    if let Some(started) = player.stock_started.modify(Option::take) {
+        player.stats.modify(|stats| {
+            stats.longest_stock_frames = stats.longest_stock_frames.max(frame.wrapping_sub(started));
+        });
+    }
+}
+
+/// Zeroes everyone's totals for a new match. Whoever is already in game starts their stock now.
+pub fn reset(info: &Info) {
+    for player in &info.players {
+        player.stats.store(Stats::default());
+        player.stock_started.store(Some(info.frame()).filter(|_| player.is_in_game()));
+    }
+}
+
+/// A player entered or respawned.
+pub fn start_stock(info: &Info, port: usize) {
+    if let Some(player) = info.players.get(port) {
+        player.stock_started.store(Some(info.frame()));
+    }
+}
+
+/// `port` took `damage`, from `attacker` if anyone else hit them.
+pub fn record_damage(info: &Info, port: usize, damage: f32, attacker: Option<usize>) {
+    if let Some(player) = info.players.get(port) {
+        player.stats.modify(|stats| stats.damage_taken += damage);
+    }
+    if let Some(attacker) = attacker.filter(|&attacker| attacker != port).and_then(|attacker| info.players.get(attacker)) {
+        attacker.stats.modify(|stats| stats.damage_dealt += damage);
+    }
+}
+
+/// `port` lost a stock at `percent`, KO'd by `killer` unless they went down on their own.
+pub fn record_fall(info: &Info, port: usize, percent: f32, killer: Option<usize>) {
+    if let Some(player) = info.players.get(port) {
+        player.stats.modify(|stats| stats.falls += 1);
+        end_stock(player, info.frame());
+    }
+    if let Some(killer) = killer.filter(|&killer| killer != port).and_then(|killer| info.players.get(killer)) {
+        killer.stats.modify(|stats| {
+            let total = stats.average_ko_percent.unwrap_or(0.) * stats.kos as f32;
+            stats.kos += 1;
+            stats.average_ko_percent = Some((total + percent) / stats.kos as f32);
+        });
+    }
+}
+
+/// Counts the stocks still in play when the match ends.
+pub fn end_match(info: &Info) {
+    for player in &info.players {
+        end_stock(player, info.frame());
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use super::*;
+    use core::sync::atomic::Ordering;
+
+    fn in_match() -> Info {
+        let info = Info::new();
+        for player in &info.players[..2] {
+            player.is_in_game.store(true, Ordering::SeqCst);
+        }
+        reset(&info);
+        info
+    }
+
+    #[test]
+    fn test_match() {
+        let info = in_match();
+        record_damage(&info, 1, 12.0, Some(0));
+        record_damage(&info, 1, 8.5, Some(0));
+        record_damage(&info, 0, 10.0, None);
+        info.frame.store(600, Ordering::SeqCst);
+        record_fall(&info, 1, 120.0, Some(0));
+
+        info.frame.store(700, Ordering::SeqCst);
+        start_stock(&info, 1);
+        info.frame.store(1000, Ordering::SeqCst);
+        record_fall(&info, 1, 80.0, Some(0));
+        info.frame.store(1300, Ordering::SeqCst);
+        end_match(&info);
+
+        assert_eq!(info.players[0].stats(), Stats {
+            damage_dealt: 20.5,
+            damage_taken: 10.0,
+            kos: 2,
+            falls: 0,
+            average_ko_percent: Some(100.0),
+            longest_stock_frames: 1300,
+        });
+        assert_eq!(info.players[1].stats(), Stats {
+            damage_dealt: 0.0,
+            damage_taken: 20.5,
+            kos: 0,
+            falls: 2,
+            average_ko_percent: None,
+            longest_stock_frames: 600,
+        });
+
+        reset(&info);
+        assert_eq!(info.players[0].stats(), Stats::default());
+    }
+
+    #[test]
+    fn test_self_destruct() {
+        let info = in_match();
+        record_damage(&info, 0, 5.0, Some(0));
+        record_fall(&info, 0, 5.0, Some(0));
+        let stats = info.players[0].stats();
+        assert_eq!((stats.damage_dealt, stats.damage_taken, stats.kos, stats.falls), (0.0, 5.0, 0, 1));
+
+        // attackers out of range are dropped rather than indexed
+        record_fall(&info, 1, 5.0, Some(8));
+    }
+}
@@ -0,0 +1,71 @@
+//! Team battles: which team each player is on, and how each team is doing.
+use serde::{Serialize, Deserialize};
+
+use crate::InfoSnapshot;
+
+/// A team's totals over its players in the match.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Team {
+    pub team: u32,
+    pub ports: Vec<usize>,
+    pub stocks: u32,
+    pub damage: f32,
+}
+
+impl Team {
+    pub fn is_eliminated(&self) -> bool {
+        self.stocks == 0
+    }
+}
+
+impl InfoSnapshot {
+    /// Every team with a player in game, by team id. Empty outside team battles.
+    pub fn teams(&self) -> Vec<Team> {
+        let mut teams: Vec<Team> = Vec::new();
+        for (port, player) in self.players.iter().enumerate() {
+            let team = match player.team {
+                Some(team) if player.is_in_game => team,
+                _ => continue,
+            };
+            let i = match teams.binary_search_by_key(&team, |t| t.team) {
+                Ok(i) => i,
+                Err(i) => {
+                    teams.insert(i, Team { team, ports: Vec::new(), stocks: 0, damage: 0.0 });
+                    i
+                }
+            };
+            teams[i].ports.push(port);
+            teams[i].stocks += player.stocks;
+            teams[i].damage += player.damage;
+        }
+        teams
+    }
+}
+
+#[cfg(test)]
+mod teams_tests {
+    use super::*;
+
+    fn doubles() -> InfoSnapshot {
+        let mut info = InfoSnapshot::default();
+        for (port, (team, stocks, damage)) in [(1, 3, 20.0), (0, 2, 45.5), (1, 1, 70.0), (0, 0, 0.0)].iter().enumerate() {
+            let player = &mut info.players[port];
+            player.is_in_game = true;
+            player.team = Some(*team);
+            player.stocks = *stocks;
+            player.damage = *damage;
+        }
+        info
+    }
+
+    #[test]
+    fn test_teams() {
+        let teams = doubles().teams();
+        assert_eq!(teams, [
+            Team { team: 0, ports: vec![1, 3], stocks: 2, damage: 45.5 },
+            Team { team: 1, ports: vec![0, 2], stocks: 4, damage: 90.0 },
+        ]);
+        assert!(!teams[0].is_eliminated());
+        assert!(InfoSnapshot::default().teams().is_empty());
+    }
+}
@@ -1,8 +1,11 @@
-use smush_info_shared::Info;
-use std::net::{TcpStream, IpAddr};
-use std::io::{BufRead, BufReader};
+use smush_info_shared::client::{Client, Update};
+use smush_info_shared::handshake::Hello;
+use smush_info_shared::discovery::{self, Discovered};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
 
 const IP_ADDR_FILE: &str = "ip_addr.txt";
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(2);
 
 fn get_home_ip_str() -> Option<String> {
     let switch_home_dir = dirs::home_dir()?.join(".switch");
@@ -18,20 +21,92 @@ fn get_home_ip_str() -> Option<String> {
     }
 }
 
-fn get_home_ip() -> IpAddr {
-    let ip = get_home_ip_str().unwrap();
-    dbg!(ip).trim().parse().unwrap()
+fn get_home_ip() -> Option<IpAddr> {
+    get_home_ip_str()?.trim().parse().ok()
 }
 
-fn get_info(bytes: &[u8]) -> Info {
-    serde_json::from_slice(bytes).unwrap()
+fn discover() -> Vec<Discovered> {
+    discovery::discover(DISCOVERY_TIMEOUT).unwrap_or_else(|err| {
+        eprintln!("discovery failed: {}", err);
+        Vec::new()
+    })
+}
+
+fn print_discovered(switches: &[Discovered]) {
+    if switches.is_empty() {
+        println!("No Switches running smush_info found");
+    }
+    for switch in switches {
+        let announcement = &switch.announcement;
+        println!(
+            "{:<16} {:<24} smush_info {} (port {})",
+            switch.ip,
+            announcement.nickname.as_deref().unwrap_or("-"),
+            announcement.plugin_version,
+            announcement.port.map_or("off".to_owned(), |port| port.to_string()),
+        );
+    }
+}
+
+/// Uses an address given on the command line, then `~/.switch/ip_addr.txt`, then the first Switch
+/// that answers a discovery probe.
+fn find_switch(arg: Option<String>) -> Option<SocketAddr> {
+    if let Some(arg) = arg {
+        return match arg.parse::<IpAddr>() {
+            Ok(ip) => Some(SocketAddr::new(ip, 4242)),
+            Err(_) => arg.parse().ok(),
+        };
+    }
+
+    if let Some(ip) = get_home_ip() {
+        return Some(SocketAddr::new(ip, 4242));
+    }
+
+    discover().iter().find_map(Discovered::addr)
+}
+
+fn print_hello(hello: &Hello) {
+    println!("smush_info {} (schema {})", hello.plugin_version, hello.schema_version);
+    println!("game version: {}", hello.game_version.as_deref().unwrap_or("unknown"));
+    for (hook, resolved) in &hello.hooks {
+        println!("  {:<24} {}", hook, if *resolved { "ok" } else { "MISSING" });
+    }
 }
 
 fn main() {
-    let packets = BufReader::new(TcpStream::connect((get_home_ip(), 4242u16)).unwrap()).split(b'\n');
+    let arg = std::env::args().nth(1);
+    if arg.as_deref() == Some("--discover") {
+        print_discovered(&discover());
+        return
+    }
 
-    for packet in packets {
-        let info = get_info(&packet.unwrap());
-        dbg!(info);
+    let addr = match find_switch(arg) {
+        Some(addr) => addr,
+        None => {
+            eprintln!("Couldn't find a Switch. Pass its IP, write it to ~/.switch/{}, or check the plugin is running on the same network.", IP_ADDR_FILE);
+            std::process::exit(1);
+        }
+    };
+
+    println!("Connecting to {}", addr);
+    let client = match Client::connect(addr) {
+        Ok(client) => client,
+        Err(err) => {
+            eprintln!("Couldn't connect to {}: {}", addr, err);
+            std::process::exit(1);
+        }
+    };
+
+    for update in client {
+        match update {
+            Ok(Update::Connected(hello)) => print_hello(&hello),
+            Ok(Update::Info(info)) => {
+                dbg!(info);
+            }
+            Ok(update) => {
+                dbg!(update);
+            }
+            Err(err) => eprintln!("{}", err),
+        }
     }
 }
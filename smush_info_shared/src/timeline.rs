@@ -0,0 +1,170 @@
+//! Every stock lost in the current match, in order.
+use serde::{Serialize, Deserialize};
+
+use crate::{clock, Info};
+
+/// How far out of the stage's plane a fighter has to go, in world units, for a top KO to be a
+/// star or screen KO.
+pub const STAR_KO_DEPTH: f32 = 30.0;
+
+/// How long after a top KO the fighter is watched for flying off into the background or the
+/// camera. The star and screen KO animations take a couple of seconds.
+pub const KO_ANIMATION_FRAMES: u32 = 3 * 60;
+
+/// The blast zone a fighter went through.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KoType {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    /// Off the top and into the background.
+    Star,
+    /// Off the top and into the camera.
+    Screen,
+}
+
+/// Where a stage's blast zones are, in world units. Stages aren't centered on the origin, so
+/// each side has its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlastZones {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl KoType {
+    /// Which blast zone a fighter dying at `x`, `y` went through: whichever one they're furthest
+    /// past, or closest to if they died inside them all. Star and screen KOs start out as
+    /// [`KoType::Top`]; see [`track_depth`].
+    pub fn from_position(x: f32, y: f32, zones: &BlastZones) -> Self {
+        let past = [
+            (KoType::Left, zones.left - x),
+            (KoType::Right, x - zones.right),
+            (KoType::Top, y - zones.top),
+            (KoType::Bottom, zones.bottom - y),
+        ];
+        past.iter()
+            .fold((KoType::Bottom, f32::NEG_INFINITY), |best, &(ko, by)| if by > best.1 { (ko, by) } else { best })
+            .0
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StockLoss {
+    pub port: usize,
+    /// Game frames since GO.
+    pub frame: u32,
+    /// The player's percent when they died.
+    pub percent: f32,
+    /// Whoever hit the player last, or `None` for a self-destruct.
+    pub killer: Option<usize>,
+    pub ko: KoType,
+}
+
+/// Empties the list for a match starting now.
+pub fn reset(info: &Info) {
+    info.stock_losses.store(Vec::new());
+}
+
+/// `port` died at `percent`. A `killer` of `port` itself counts as a self-destruct.
+pub fn record(info: &Info, port: usize, percent: f32, killer: Option<usize>, ko: KoType) {
+    let frame = clock::elapsed(info);
+    let killer = killer.filter(|&killer| killer != port && killer < info.players.len());
+    info.stock_losses.modify(|losses| losses.push(StockLoss { port, frame, percent, killer, ko }));
+}
+
+/// Called every frame with how far `port`'s fighter is out of the stage's plane, negative into the
+/// background. Star and screen KOs only show once the fighter has gone off the top, so a fighter
+/// far into the background soon after a top KO turns it into a star KO, and one far toward the
+/// camera into a screen KO.
+pub fn track_depth(info: &Info, port: usize, depth: f32) {
+    if depth.abs() < STAR_KO_DEPTH {
+        return;
+    }
+    let ko = if depth < 0.0 { KoType::Star } else { KoType::Screen };
+    let frame = clock::elapsed(info);
+    info.stock_losses.modify(|losses| {
+        if let Some(loss) = losses.iter_mut().rev().find(|loss| loss.port == port) {
+            if loss.ko == KoType::Top && frame.wrapping_sub(loss.frame) <= KO_ANIMATION_FRAMES {
+                loss.ko = ko;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod timeline_tests {
+    use super::*;
+    use core::sync::atomic::Ordering;
+    use crate::clock::MatchMode;
+
+    #[test]
+    fn test_record() {
+        let info = Info::new();
+        info.frame.store(1000, Ordering::SeqCst);
+        reset(&info);
+        clock::start(&info);
+        clock::update(&info, u32::MAX, true, MatchMode::Stock);
+
+        info.frame.store(1600, Ordering::SeqCst);
+        record(&info, 1, 132.5, Some(0), KoType::Right);
+        info.frame.store(2000, Ordering::SeqCst);
+        record(&info, 0, 12.0, Some(0), KoType::Bottom);
+
+        assert_eq!(info.stock_losses.load(), [
+            StockLoss { port: 1, frame: 600, percent: 132.5, killer: Some(0), ko: KoType::Right },
+            StockLoss { port: 0, frame: 1000, percent: 12.0, killer: None, ko: KoType::Bottom },
+        ]);
+        assert_eq!(info.snapshot().stock_losses.len(), 2);
+
+        reset(&info);
+        assert!(info.stock_losses.load().is_empty());
+    }
+
+    #[test]
+    fn test_track_depth() {
+        let info = Info::new();
+        reset(&info);
+        clock::start(&info);
+        clock::update(&info, u32::MAX, true, MatchMode::Stock);
+
+        record(&info, 0, 150.0, Some(1), KoType::Top);
+        record(&info, 1, 90.0, Some(0), KoType::Top);
+        record(&info, 2, 60.0, Some(0), KoType::Left);
+        info.frame.store(40, Ordering::SeqCst);
+        // drifting a little isn't enough
+        track_depth(&info, 0, -5.0);
+        track_depth(&info, 0, -80.0);
+        track_depth(&info, 1, 45.0);
+        // only top KOs become star or screen KOs
+        track_depth(&info, 2, -80.0);
+        let kos: Vec<_> = info.stock_losses().iter().map(|loss| loss.ko).collect();
+        assert_eq!(kos, [KoType::Star, KoType::Screen, KoType::Left]);
+
+        // long after the KO, the fighter has respawned and moving about says nothing
+        record(&info, 0, 40.0, Some(1), KoType::Top);
+        info.frame.store(40 + KO_ANIMATION_FRAMES + 1, Ordering::SeqCst);
+        track_depth(&info, 0, -80.0);
+        assert_eq!(info.stock_losses()[3].ko, KoType::Top);
+    }
+
+    #[test]
+    fn test_from_position() {
+        let zones = BlastZones { left: -240.0, right: 240.0, top: 200.0, bottom: -140.0 };
+        assert_eq!(KoType::from_position(-250.0, 30.0, &zones), KoType::Left);
+        assert_eq!(KoType::from_position(245.0, -100.0, &zones), KoType::Right);
+        assert_eq!(KoType::from_position(40.0, -150.0, &zones), KoType::Bottom);
+        assert_eq!(KoType::from_position(-60.0, 220.0, &zones), KoType::Top);
+
+        // further out sideways than down, but only the bottom zone is near
+        assert_eq!(KoType::from_position(-170.0, -141.0, &zones), KoType::Bottom);
+
+        // a stage set off to one side
+        let zones = BlastZones { left: -100.0, right: 300.0, top: 250.0, bottom: -60.0 };
+        assert_eq!(KoType::from_position(290.0, -70.0, &zones), KoType::Bottom);
+        assert_eq!(KoType::from_position(-110.0, 30.0, &zones), KoType::Left);
+    }
+}
@@ -0,0 +1,285 @@
+//! Server side of the WebSocket protocol (RFC 6455), so browser sources can subscribe directly.
+//!
+//! Every message the plugin would write as a line goes out as one text frame instead, and text
+//! frames from the browser are read as [`Command`](crate::protocol::Command)s.
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+
+use crate::http::Request;
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Longest message accepted from a client. They only ever send commands.
+pub const MAX_MESSAGE_LEN: usize = crate::protocol::MAX_FRAME_LEN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    fn from_u8(op: u8) -> Option<Self> {
+        Some(match op {
+            0x0 => Opcode::Continuation,
+            0x1 => Opcode::Text,
+            0x2 => Opcode::Binary,
+            0x8 => Opcode::Close,
+            0x9 => Opcode::Ping,
+            0xa => Opcode::Pong,
+            _ => return None,
+        })
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            Opcode::Continuation => 0x0,
+            Opcode::Text => 0x1,
+            Opcode::Binary => 0x2,
+            Opcode::Close => 0x8,
+            Opcode::Ping => 0x9,
+            Opcode::Pong => 0xa,
+        }
+    }
+
+    fn is_control(self) -> bool {
+        matches!(self, Opcode::Close | Opcode::Ping | Opcode::Pong)
+    }
+}
+
+/// A complete message; fragmented data messages are reassembled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub opcode: Opcode,
+    pub payload: Vec<u8>,
+}
+
+struct Frame {
+    fin: bool,
+    opcode: Opcode,
+    payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebSocketError {
+    NotAnUpgrade,
+    BadOpcode(u8),
+    Unmasked,
+    TooLong,
+    UnexpectedContinuation,
+}
+
+/// Computes `Sec-WebSocket-Accept` for a client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    let mut sha1 = sha1_smol::Sha1::new();
+    sha1.update(key.trim().as_bytes());
+    sha1.update(ACCEPT_GUID.as_bytes());
+    BASE64.encode(sha1.digest().bytes())
+}
+
+/// Builds the `101 Switching Protocols` response for an upgrade request.
+pub fn upgrade_response(request: &Request) -> Result<Vec<u8>, WebSocketError> {
+    let is_upgrade = request.method == "GET"
+        && request.header_has_token("Connection", "upgrade")
+        && request.header_has_token("Upgrade", "websocket");
+
+    let key = match request.header("Sec-WebSocket-Key") {
+        Some(key) if is_upgrade => key,
+        _ => return Err(WebSocketError::NotAnUpgrade),
+    };
+
+    Ok(format!(
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    ).into_bytes())
+}
+
+/// Encodes a single unmasked, unfragmented frame, as servers send them.
+pub fn encode_frame(opcode: Opcode, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode.as_u8());
+
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Accumulates bytes from a client and splits them into [`Message`]s.
+#[derive(Default)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    fragments: Option<Message>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn next_message(&mut self) -> Option<Result<Message, WebSocketError>> {
+        loop {
+            let Frame { fin, opcode, payload } = match self.next_frame()? {
+                Ok(frame) => frame,
+                Err(err) => return Some(Err(err)),
+            };
+
+            // control frames may arrive in the middle of a fragmented message
+            if opcode.is_control() {
+                return Some(Ok(Message { opcode, payload }));
+            }
+
+            let message = match (opcode, self.fragments.take()) {
+                (Opcode::Continuation, Some(mut message)) => {
+                    message.payload.extend_from_slice(&payload);
+                    message
+                }
+                (Opcode::Continuation, None) => return Some(Err(WebSocketError::UnexpectedContinuation)),
+                (opcode, _) => Message { opcode, payload },
+            };
+
+            if message.payload.len() > MAX_MESSAGE_LEN {
+                return Some(Err(WebSocketError::TooLong));
+            }
+
+            if fin {
+                return Some(Ok(message));
+            }
+            self.fragments = Some(message);
+        }
+    }
+
+    fn next_frame(&mut self) -> Option<Result<Frame, WebSocketError>> {
+        if self.buf.len() < 2 {
+            return None;
+        }
+
+        let fin = self.buf[0] & 0x80 != 0;
+        let opcode = match Opcode::from_u8(self.buf[0] & 0x0f) {
+            Some(opcode) => opcode,
+            None => return Some(Err(WebSocketError::BadOpcode(self.buf[0] & 0x0f))),
+        };
+        if self.buf[1] & 0x80 == 0 {
+            return Some(Err(WebSocketError::Unmasked));
+        }
+
+        let (len, mut pos) = match self.buf[1] & 0x7f {
+            126 if self.buf.len() >= 4 => (u16::from_be_bytes([self.buf[2], self.buf[3]]) as u64, 4),
+            127 if self.buf.len() >= 10 => {
+                let mut len = [0; 8];
+                len.copy_from_slice(&self.buf[2..10]);
+                (u64::from_be_bytes(len), 10)
+            }
+            126 | 127 => return None,
+            len => (len as u64, 2),
+        };
+
+        if len > MAX_MESSAGE_LEN as u64 {
+            return Some(Err(WebSocketError::TooLong));
+        }
+
+        let len = len as usize;
+        if self.buf.len() < pos + 4 + len {
+            return None;
+        }
+
+        let mut mask = [0; 4];
+        mask.copy_from_slice(&self.buf[pos..pos + 4]);
+        pos += 4;
+
+        let payload = self.buf[pos..pos + len]
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ mask[i % 4])
+            .collect();
+        self.buf.drain(..pos + len);
+
+        Some(Ok(Frame { fin, opcode, payload }))
+    }
+}
+
+#[cfg(test)]
+mod websocket_tests {
+    use super::*;
+    use crate::http::parse_request;
+
+    fn masked(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x37, 0xfa, 0x21, 0x3d];
+        let mut frame = vec![first, 0x80 | payload.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // example from RFC 6455 section 1.3
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn test_upgrade_response() {
+        let raw = b"GET / HTTP/1.1\r\nHost: switch\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                    Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n";
+        let (request, _) = parse_request(raw).unwrap().unwrap();
+        let response = String::from_utf8(upgrade_response(&request).unwrap()).unwrap();
+        assert!(response.starts_with("HTTP/1.1 101"));
+        assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+        let raw = b"GET /info HTTP/1.1\r\nHost: switch\r\n\r\n";
+        let (request, _) = parse_request(raw).unwrap().unwrap();
+        assert_eq!(upgrade_response(&request), Err(WebSocketError::NotAnUpgrade));
+    }
+
+    #[test]
+    fn test_encode_frame_lengths() {
+        assert_eq!(encode_frame(Opcode::Text, b"hi"), [0x81, 2, b'h', b'i']);
+        assert_eq!(&encode_frame(Opcode::Text, &[0; 200])[..4], [0x81, 126, 0, 200]);
+        assert_eq!(&encode_frame(Opcode::Binary, &[0; 70000])[..10], [0x82, 127, 0, 0, 0, 0, 0, 1, 0x11, 0x70]);
+    }
+
+    #[test]
+    fn test_decode_fragmented() {
+        let mut decoder = FrameDecoder::new();
+        decoder.extend(&masked(0x01, b"{\"command\":"));
+        decoder.extend(&masked(0x89, b"")); // ping in between fragments
+        let frame = masked(0x80, b"\"ping\"}");
+        decoder.extend(&frame[..3]);
+
+        assert_eq!(decoder.next_message(), Some(Ok(Message { opcode: Opcode::Ping, payload: vec![] })));
+        assert_eq!(decoder.next_message(), None);
+
+        decoder.extend(&frame[3..]);
+        let message = decoder.next_message().unwrap().unwrap();
+        assert_eq!(message.opcode, Opcode::Text);
+        assert_eq!(message.payload, b"{\"command\":\"ping\"}");
+    }
+
+    #[test]
+    fn test_reject_unmasked() {
+        let mut decoder = FrameDecoder::new();
+        decoder.extend(&encode_frame(Opcode::Text, b"hi"));
+        assert_eq!(decoder.next_message(), Some(Err(WebSocketError::Unmasked)));
+    }
+}
@@ -0,0 +1,103 @@
+use skyline::libc::*;
+use skyline::from_c_str;
+use std::mem::size_of_val;
+use lazy_static::lazy_static;
+
+use smush_info_shared::discovery::{self, Announcement, DISCOVERY_PORT};
+
+use crate::CONFIG;
+
+lazy_static! {
+    // the console itself has no nickname readable from a game, so use the last user's instead
+    static ref NICKNAME: Option<String> = unsafe {
+        let mut uid = skyline::nn::account::Uid { id: [0; 2] };
+        let mut nickname = skyline::nn::account::Nickname { name: [0; 33] };
+        skyline::nn::account::GetLastOpenedUser(&mut uid);
+        skyline::nn::account::GetNickname(&mut nickname, &uid);
+        let nickname = from_c_str(nickname.name.as_ptr() as _);
+        if nickname.is_empty() {
+            None
+        } else {
+            Some(nickname)
+        }
+    };
+}
+
+fn announcement() -> Announcement {
+    let (server, transports) = (&CONFIG.server, &CONFIG.transports);
+    Announcement {
+        nickname: NICKNAME.clone(),
+        plugin_version: env!("CARGO_PKG_VERSION").to_owned(),
+        port: Some(server.port).filter(|_| transports.tcp),
+        websocket_port: Some(server.websocket_port).filter(|_| transports.websocket),
+        http_port: Some(server.http_port).filter(|_| transports.http),
+    }
+}
+
+/// Answers discovery probes broadcast by clients on the LAN until the socket fails.
+pub fn start_responder() -> Result<(), i64> {
+    unsafe {
+        let server_addr: sockaddr_in = sockaddr_in {
+            sin_family: AF_INET as _,
+            sin_port: DISCOVERY_PORT.to_be(),
+            sin_len: 4,
+            sin_addr: in_addr {
+                // broadcasts don't reach a socket bound to a unicast address, so ignore listen_address
+                s_addr: INADDR_ANY as _,
+            },
+            sin_zero: [0; 8],
+        };
+
+        let udp_socket = socket(AF_INET, SOCK_DGRAM, 0);
+        if (udp_socket as u32 & 0x80000000) != 0 {
+            let errno = *errno_loc();
+            dbg!(errno);
+            return Err(errno);
+        }
+
+        if bind(
+            udp_socket,
+            &server_addr as *const sockaddr_in as *const sockaddr,
+            size_of_val(&server_addr) as u32,
+        ) < 0 {
+            let errno = *errno_loc();
+            dbg!(errno);
+            close(udp_socket);
+            return Err(errno);
+        }
+
+        let mut buf = [0u8; 0x40];
+        loop {
+            let mut client_addr = server_addr;
+            let mut addr_len = size_of_val(&client_addr) as u32;
+
+            let len = recvfrom(
+                udp_socket,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+                0,
+                &mut client_addr as *mut sockaddr_in as *mut sockaddr,
+                &mut addr_len,
+            );
+            if len < 0 {
+                let errno = *errno_loc();
+                close(udp_socket);
+                return Err(errno);
+            }
+
+            if !discovery::is_probe(&buf[..len as usize]) {
+                continue
+            }
+
+            let reply = announcement().encode();
+            sendto(
+                udp_socket,
+                reply.as_ptr() as *const c_void,
+                reply.len(),
+                0,
+                &client_addr as *const sockaddr_in as *const sockaddr,
+                addr_len,
+            );
+        }
+    }
+}
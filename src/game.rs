@@ -0,0 +1,165 @@
+use std::convert::TryFrom;
+use smash::app;
+use smash::app::lua_bind::*;
+use smash::lib::lua_const::*;
+
+use smush_info_shared::Character;
+use smush_info_shared::backend::{GameBackend, Fighter};
+use smush_info_shared::clock::MatchMode;
+use smush_info_shared::timeline::{BlastZones, KoType};
+
+use crate::conversions::kind_to_char;
+use crate::{GAME_INFO, FIGHTER_MANAGER_ADDR, offset_to_addr, get_kind, get_remaining_time_as_frame, get_dead_range, get_tag_of_player};
+
+const CURRENT_MENU_OFFSET: usize = 0x53050f0;
+
+fn fighter_manager() -> *mut app::FighterManager {
+    unsafe { *(FIGHTER_MANAGER_ADDR as *mut *mut app::FighterManager) }
+}
+
+fn fighter_information(port: usize) -> *mut app::FighterInformation {
+    unsafe {
+        FighterManager::get_fighter_information(
+            fighter_manager(),
+            app::FighterEntryID(port as i32)
+        ) as *mut app::FighterInformation
+    }
+}
+
+/// The running game.
+pub struct Game;
+
+impl GameBackend for Game {
+    fn fighter_entry_count(&self) -> Option<u32> {
+        let mgr = fighter_manager();
+        if mgr.is_null() {
+            return None;
+        }
+        Some(unsafe { FighterManager::entry_count(mgr) } as u32)
+    }
+
+    fn is_result_mode(&self) -> bool {
+        unsafe { FighterManager::is_result_mode(fighter_manager()) }
+    }
+
+    fn current_menu(&self) -> u32 {
+        unsafe { *(offset_to_addr(CURRENT_MENU_OFFSET) as *const u32) }
+    }
+
+    fn remaining_time_as_frame(&self) -> u32 {
+        unsafe { get_remaining_time_as_frame() }
+    }
+
+    fn is_ready_go(&self) -> bool {
+        unsafe { FighterManager::is_ready_go(fighter_manager()) }
+    }
+
+    fn match_mode(&self) -> MatchMode {
+        // stamina fighters have HP, and time battles count KOs rather than stocks
+        let port = match GAME_INFO.players.iter().position(|player| player.is_in_game()) {
+            Some(port) => port,
+            None => return MatchMode::Stock,
+        };
+        let information = fighter_information(port);
+        unsafe {
+            if FighterInformation::hit_point_max(information) > 0.0 {
+                MatchMode::Stamina
+            } else if FighterInformation::stock_count(information) == 0 {
+                MatchMode::Time
+            } else {
+                MatchMode::Stock
+            }
+        }
+    }
+
+    fn player_tag(&self, port: usize) -> String {
+        get_tag_of_player(port)
+    }
+
+    fn placement(&self, port: usize) -> Option<u32> {
+        // the game's own ranking, as shown on the results screen (0 for first)
+        let rank = unsafe { FighterInformation::summary_rank(fighter_information(port)) };
+        u32::try_from(rank).ok().map(|rank| rank + 1)
+    }
+
+    fn friendly_fire(&self) -> bool {
+        unsafe { FighterManager::is_team_attack(fighter_manager()) }
+    }
+}
+
+/// The fighter a status hook was called for.
+pub struct HookedFighter {
+    lua_state: u64,
+    module_accessor: *mut app::BattleObjectModuleAccessor,
+}
+
+impl HookedFighter {
+    pub fn new(lua_state: u64) -> Self {
+        let module_accessor = unsafe { app::sv_system::battle_object_module_accessor(lua_state) };
+        Self { lua_state, module_accessor }
+    }
+
+    fn information(&self) -> *mut app::FighterInformation {
+        fighter_information(self.entry_id())
+    }
+}
+
+impl Fighter for HookedFighter {
+    fn entry_id(&self) -> usize {
+        unsafe { WorkModule::get_int(self.module_accessor, *FIGHTER_INSTANCE_WORK_ID_INT_ENTRY_ID) as usize }
+    }
+
+    fn character(&self) -> Character {
+        kind_to_char(unsafe { get_kind(&mut *self.module_accessor) })
+    }
+
+    fn damage(&self) -> f32 {
+        unsafe { DamageModule::damage(self.module_accessor, 0) }
+    }
+
+    fn stock_count(&self) -> u32 {
+        unsafe { FighterInformation::stock_count(self.information()) as u32 }
+    }
+
+    fn self_destructs(&self) -> u32 {
+        unsafe { FighterInformation::suicide_count(self.information(), 0) as u32 }
+    }
+
+    fn is_cpu(&self) -> bool {
+        unsafe { FighterInformation::is_operation_cpu(self.information()) }
+    }
+
+    fn skin(&self) -> u32 {
+        unsafe { WorkModule::get_int(self.module_accessor, *FIGHTER_INSTANCE_WORK_ID_INT_COLOR) as u32 }
+    }
+
+    fn last_attacker(&self) -> Option<usize> {
+        // -1 until someone hits the fighter; items and stage hazards may leave other values
+        let entry_id = unsafe {
+            WorkModule::get_int(self.module_accessor, *FIGHTER_INSTANCE_WORK_ID_INT_LAST_ATTACK_ENTRY_ID)
+        };
+        usize::try_from(entry_id).ok().filter(|&entry_id| entry_id < 8)
+    }
+
+    fn ko_type(&self) -> KoType {
+        unsafe {
+            let range = get_dead_range(self.lua_state);
+            let zones = BlastZones { left: range.left, right: range.right, top: range.top, bottom: range.bottom };
+            KoType::from_position(PostureModule::pos_x(self.module_accessor), PostureModule::pos_y(self.module_accessor), &zones)
+        }
+    }
+
+    fn depth(&self) -> f32 {
+        unsafe { PostureModule::pos_z(self.module_accessor) }
+    }
+
+    fn team(&self) -> Option<u32> {
+        unsafe {
+            if !FighterManager::is_team_battle(fighter_manager()) {
+                return None;
+            }
+            // teams go by color, so this is the team's
+            Some(FighterInformation::fighter_color(self.information()) as u32)
+        }
+    }
+}
@@ -4,9 +4,9 @@
 use skyline::hooks::{getRegionAddress, Region, InlineCtx};
 use skyline::from_c_str;
 use skyline::libc::*;
-use std::time::Duration;
-use std::mem::size_of_val;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::BTreeMap;
+use lazy_static::lazy_static;
 
 use smash::app;
 use smash::app::lua_bind;
@@ -17,13 +17,21 @@ use smash::lua2cpp::{L2CFighterCommon, L2CFighterCommon_status_pre_Rebirth, L2CF
 use smash::lib::L2CValue;
 
 use smush_info_shared::Info;
+use smush_info_shared::backend::{self, FrameTicker};
+use smush_info_shared::config::{Config, CONFIG_PATH};
+use smush_info_shared::events::EventDeriver;
 
 use core::arch::aarch64::*;
 use smash::Vector3f;
 use smash::Vector2f;
 
 mod conversions;
+mod discovery;
+mod game;
+mod server;
+mod transport;
 use conversions::{kind_to_char, stage_id_to_stage};
+use game::{Game, HookedFighter};
 
 static mut OFFSET1 : usize = 0x1b52a0;
 static mut OFFSET2 : usize = 0x225dc2c;
@@ -32,6 +40,46 @@ static mut OFFSET3 : usize = 0xd7140;
 // Default 13.0.2 offset
 static mut FIGHTER_SELECTED_OFFSET: usize = 0x66e140;
 
+// whether each signature search found its target, reported to clients in the handshake
+static ARENA_ID_OFFSETS_FOUND: AtomicBool = AtomicBool::new(false);
+static CLOSE_ARENA_OFFSET_FOUND: AtomicBool = AtomicBool::new(false);
+static FIGHTER_SELECTED_OFFSET_FOUND: AtomicBool = AtomicBool::new(false);
+static COMMON_HOOKS_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    pub static ref CONFIG: Config = {
+        let (config, err) = Config::load_or_default(CONFIG_PATH);
+        if let Some(err) = err {
+            println!("[smush_info] {}. Falling back to the default config.", err);
+        }
+        config
+    };
+
+    static ref GAME_VERSION: Option<String> = unsafe {
+        let mut version = skyline::nn::oe::DisplayVersion { name: [0; 16] };
+        skyline::nn::oe::GetDisplayVersion(&mut version);
+        let version = from_c_str(version.name.as_ptr() as _);
+        if version.is_empty() {
+            None
+        } else {
+            Some(version)
+        }
+    };
+}
+
+pub fn game_version() -> Option<String> {
+    GAME_VERSION.clone()
+}
+
+pub fn resolved_hooks() -> BTreeMap<String, bool> {
+    let mut hooks = BTreeMap::new();
+    hooks.insert("arena_id".to_owned(), ARENA_ID_OFFSETS_FOUND.load(Ordering::SeqCst));
+    hooks.insert("close_arena".to_owned(), CLOSE_ARENA_OFFSET_FOUND.load(Ordering::SeqCst));
+    hooks.insert("css_fighter_selected".to_owned(), FIGHTER_SELECTED_OFFSET_FOUND.load(Ordering::SeqCst));
+    hooks.insert("fighter_status".to_owned(), COMMON_HOOKS_INSTALLED.load(Ordering::SeqCst));
+    hooks
+}
+
 static FIGHTER_SELECTED_SEARCH_CODE: &[u8] = &[
     0xb0, 0xde, 0x45, 0x94,
     0xe0, 0x03, 0x1c, 0x32,
@@ -50,17 +98,18 @@ extern "C" {
     
     #[link_name = "\u{1}_ZN3app17sv_camera_manager15world_to_screenERKN3phx8Vector3fEb"]
     pub fn world_to_screen(vec: *const Vector3f, unk: bool) -> float32x2_t;
+
+    #[link_name = "\u{1}_ZN3app5stage14get_dead_rangeEP9lua_State"]
+    pub fn get_dead_range(lua_state: u64) -> DeadRange;
 }
 
-fn send_bytes(socket: i32, bytes: &[u8]) -> Result<(), i64> {
-    unsafe {
-        let ret = send(socket, bytes.as_ptr() as *const _, bytes.len(), 0);
-        if ret < 0 {
-            Err(*errno_loc())
-        } else {
-            Ok(())
-        }
-    }
+/// The current stage's blast zones, in world units.
+#[repr(C)]
+pub struct DeadRange {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
 }
 
 fn as_pixels(vec: Vector3f) -> Vector2f {
@@ -89,124 +138,23 @@ pub fn once_per_frame_per_fighter(fighter : &mut L2CFighterCommon) {
     
         GAME_INFO.players[player_num].x.store(screen_pos.x, Ordering::SeqCst);
         GAME_INFO.players[player_num].y.store(screen_pos.y, Ordering::SeqCst);
-    }
-
-}
-
 
-static GAME_INFO: Info = Info::new();
-
-#[allow(unreachable_code)]
-fn start_server() -> Result<(), i64> {
-    unsafe {
-        let server_addr: sockaddr_in = sockaddr_in {
-            sin_family: AF_INET as _,
-            sin_port: 4242u16.to_be(),
-            sin_len: 4,
-            sin_addr: in_addr {
-                s_addr: INADDR_ANY as _,
-            },
-            sin_zero: [0; 8],
-        };
-
-        let tcp_socket = socket(AF_INET, SOCK_STREAM, 0);
-
-        macro_rules! dbg_err {
-            ($expr:expr) => {
-                let rval = $expr;
-                if rval < 0 {
-                    let errno = *errno_loc();
-                    dbg!(errno);
-                    close(tcp_socket);
-                    return Err(errno);
-                }
-            };
-        }
+        backend::handle_fighter_frame(&GAME_INFO, &HookedFighter::new(lua_state));
 
-        if (tcp_socket as u32 & 0x80000000) != 0 {
-            let errno = *errno_loc();
-            dbg!(errno);
-            return Err(errno);
+        // this runs for every fighter object, so the ticker makes it once a frame
+        if FRAMES.tick(module_accessor as usize) {
+            server::advance_frame();
         }
+    }
 
-        let flags: u32 = 1;
-
-        dbg_err!(setsockopt(
-            tcp_socket,
-            SOL_SOCKET,
-            SO_KEEPALIVE,
-            &flags as *const _ as *const c_void,
-            size_of_val(&flags) as u32,
-        ));
-
-        dbg_err!(bind(
-            tcp_socket,
-            &server_addr as *const sockaddr_in as *const sockaddr,
-            size_of_val(&server_addr) as u32,
-        ));
-
-        dbg_err!(listen(tcp_socket, 1));
-
-        let mut addr_len: u32 = 0;
-
-        let mut w_tcp_socket = accept(
-            tcp_socket,
-            &server_addr as *const sockaddr_in as *mut sockaddr,
-            &mut addr_len,
-        );
-
-        loop {
-            let mgr = *(FIGHTER_MANAGER_ADDR as *mut *mut app::FighterManager);
-            let is_match = FighterManager::entry_count(mgr) > 0 &&
-                !FighterManager::is_result_mode(mgr) &&
-                *(offset_to_addr(0x53050f0) as *const u32) != 0x6020000; //is_match is set to true when the player in the controls screen, i assume because there is a sandbag and mario. this ensures we're not in the controls screen 
-
-            if is_match {
-                GAME_INFO.remaining_frames.store(get_remaining_time_as_frame(), Ordering::SeqCst);
-                GAME_INFO.is_match.store(true, Ordering::SeqCst);
-            } else {
-                GAME_INFO.remaining_frames.store(-1.0 as u32, Ordering::SeqCst);
-                GAME_INFO.is_match.store(false, Ordering::SeqCst);
-                for player in &GAME_INFO.players {
-                    player.is_in_game.store(false, Ordering::SeqCst);
-                }
-            }
+}
 
-            GAME_INFO.current_menu.store(*(offset_to_addr(0x53050f0) as *const u32), Ordering::SeqCst);
-            if(FighterManager::entry_count(mgr) > 0 && *(offset_to_addr(0x53050f0) as *const u32) != 0x6020000) {
-                GAME_INFO.is_results_screen.store(FighterManager::is_result_mode(mgr), Ordering::SeqCst);
-            }
 
-            let mut data = serde_json::to_vec(&GAME_INFO).unwrap();
-            data.push(b'\n');
-            match send_bytes(w_tcp_socket, &data) {
-                Ok(_) => (),
-                Err(32) => {
-                    w_tcp_socket = accept(
-                        tcp_socket,
-                        &server_addr as *const sockaddr_in as *mut sockaddr,
-                        &mut addr_len,
-                    );
-                }
-                Err(e) => {
-                    println!("send_bytes errno = {}", e);
-                }
-            }
-            std::thread::sleep(Duration::from_millis(160));
-        }
-        /*let magic = recv_bytes(tcp_socket, 4).unwrap();
-        if &magic == b"HRLD" {
-            let num_bytes = recv_u32(tcp_socket).unwrap();
-        } else if &magic == b"ECHO" {
-            println!("\n\n----\nECHO\n\n");
-        } else {
-            println!("Invalid magic")
-        }*/
-        
-        dbg_err!(close(tcp_socket));
-    }
+static GAME_INFO: Info = Info::new();
+static FRAMES: FrameTicker = FrameTicker::new();
 
-    Ok(())
+fn update_game_info() {
+    backend::update_match_state(&GAME_INFO, &Game);
 }
 
 pub fn offset_to_addr(offset: usize) -> *const () {
@@ -308,41 +256,9 @@ fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
 
 pub static mut FIGHTER_MANAGER_ADDR: usize = 0;
 
-pub unsafe fn set_player_information(module_accessor: &mut app::BattleObjectModuleAccessor) {
-    let entry_id = WorkModule::get_int(module_accessor, *FIGHTER_INSTANCE_WORK_ID_INT_ENTRY_ID) as i32;
-    let player_num = entry_id as usize;
-    let mgr = *(FIGHTER_MANAGER_ADDR as *mut *mut app::FighterManager);
-    let fighter_information = FighterManager::get_fighter_information(
-        mgr, 
-        app::FighterEntryID(entry_id)
-    ) as *mut app::FighterInformation;
-
-    let character = kind_to_char(get_kind(module_accessor)) as u32;
-    let damage = DamageModule::damage(module_accessor, 0);
-    let stock_count = FighterInformation::stock_count(fighter_information) as u32;
-    let sd_count = FighterInformation::suicide_count(fighter_information, 0) as u32;
-    let is_cpu = FighterInformation::is_operation_cpu(fighter_information);
-    let skin = (WorkModule::get_int(module_accessor, *FIGHTER_INSTANCE_WORK_ID_INT_COLOR)) as u32; //returns costume slot 0-indexed
-
-    if(FighterManager::entry_count(mgr) > 0) {
-        GAME_INFO.players[player_num].hero_menu_selected.store(false, Ordering::SeqCst);
-        GAME_INFO.players[player_num].hero_menu_open.store(false, Ordering::SeqCst);            
-    }
-    GAME_INFO.players[player_num].is_in_game.store(true, Ordering::SeqCst);
-    GAME_INFO.players[player_num].character.store(character, Ordering::SeqCst);
-    GAME_INFO.players[player_num].damage.store(damage, Ordering::SeqCst);
-    GAME_INFO.players[player_num].stocks.store(stock_count, Ordering::SeqCst);
-    GAME_INFO.players[player_num].self_destructs.store(sd_count, Ordering::SeqCst);
-    GAME_INFO.players[player_num].is_cpu.store(is_cpu, Ordering::SeqCst);
-    GAME_INFO.players[player_num].skin.store(skin, Ordering::SeqCst);
-    println!("ZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZ player tag {}", get_tag_of_player(player_num));
-    GAME_INFO.players[player_num].name.store_str(Some(&get_tag_of_player(player_num)), Ordering::SeqCst);
-}
-
 #[skyline::hook(replace = L2CFighterCommon_status_pre_Entry)]
 pub unsafe fn handle_pre_entry(fighter: &mut L2CFighterCommon) -> L2CValue {
-    let module_accessor = app::sv_system::battle_object_module_accessor(fighter.lua_state_agent);
-    set_player_information(module_accessor);
+    backend::handle_stock_start(&GAME_INFO, &Game, &HookedFighter::new(fighter.lua_state_agent));
 
     GAME_INFO.stage.store(stage_id_to_stage(stage_id()) as u32, Ordering::SeqCst);
 
@@ -351,64 +267,49 @@ pub unsafe fn handle_pre_entry(fighter: &mut L2CFighterCommon) -> L2CValue {
 
 #[skyline::hook(replace = L2CFighterCommon_status_pre_Rebirth)]
 pub unsafe fn handle_pre_rebirth(fighter: &mut L2CFighterCommon) -> L2CValue {
-    let module_accessor = app::sv_system::battle_object_module_accessor(fighter.lua_state_agent);
-    set_player_information(module_accessor);
+    backend::handle_stock_start(&GAME_INFO, &Game, &HookedFighter::new(fighter.lua_state_agent));
 
     original!()(fighter)
 }
 
 #[skyline::hook(replace = L2CFighterCommon_status_pre_Dead)]
-pub unsafe fn handle_pre_dead(fighter: &mut L2CFighterCommon) -> L2CValue { // this kinda fucking sucks but whatever
-    let module_accessor = app::sv_system::battle_object_module_accessor(fighter.lua_state_agent);
-
-    let entry_id = WorkModule::get_int(module_accessor, *FIGHTER_INSTANCE_WORK_ID_INT_ENTRY_ID) as i32;
-    let player_num = entry_id as usize;
-    let mgr = *(FIGHTER_MANAGER_ADDR as *mut *mut app::FighterManager);
-    let fighter_information = FighterManager::get_fighter_information(
-        mgr, 
-        app::FighterEntryID(entry_id)
-    ) as *mut app::FighterInformation;
-
-    let stock_count = (FighterInformation::stock_count(fighter_information) as u32) - 1;
-    GAME_INFO.players[player_num].stocks.store(stock_count, Ordering::SeqCst);
-
-    println!("ZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZ WE CALLED L2CFighterCommon_status_pre_Dead AND ATTEMPTED TO SET STOCK COUNT TO {}", stock_count);
-    // set_player_information(module_accessor);
+pub unsafe fn handle_pre_dead(fighter: &mut L2CFighterCommon) -> L2CValue {
+    backend::handle_pre_dead(&GAME_INFO, &HookedFighter::new(fighter.lua_state_agent));
 
     original!()(fighter)
 }
 
 #[skyline::hook(replace = L2CFighterCommon_sub_damage_uniq_process_init)]
 pub unsafe fn handle_sub_damage_uniq_process_init(fighter: &mut L2CFighterCommon) -> L2CValue {
-    let module_accessor = app::sv_system::battle_object_module_accessor(fighter.lua_state_agent);
-    set_player_information(module_accessor);
+    backend::handle_damage(&GAME_INFO, &Game, &HookedFighter::new(fighter.lua_state_agent));
 
     original!()(fighter)
 }
 
 fn nro_main(nro: &skyline::nro::NroInfo<'_>) {
     match nro.name {
-        "common" => {
+        "common" if CONFIG.hooks.fighter_status => {
             skyline::install_hooks!(
                 handle_pre_entry,
                 handle_pre_rebirth,
                 handle_sub_damage_uniq_process_init,
                 handle_pre_dead
             );
+            COMMON_HOOKS_INSTALLED.store(true, Ordering::SeqCst);
         }
         _ => (),
     }
 }
 
 
-static UPDATE_TAG_FOR_PLAYER_OFFSET: usize = 0x19fd090;
-static PLAYER_SAVE_OFFSET: usize = 0x5314510;
+static mut UPDATE_TAG_FOR_PLAYER_OFFSET: usize = 0x19fd090;
+static mut PLAYER_SAVE_OFFSET: usize = 0x5314510;
 static mut PLAYER_SAVE_ADDRESS: *const u64 = 0x0 as *const u64;
 
-static PLAYER_TAG_OFFSET: usize = 0x52c5758;
+static mut PLAYER_TAG_OFFSET: usize = 0x52c5758;
 
 pub fn get_tag_of_player(player_index: usize) -> String {
-    let player_tag_offset = PLAYER_TAG_OFFSET + (player_index * 0x260);
+    let player_tag_offset = unsafe { PLAYER_TAG_OFFSET } + (player_index * 0x260);
     let player_tag_addr: *const u16 = offset_to_addr(player_tag_offset) as *const u16;
     
     unsafe {
@@ -508,21 +409,61 @@ fn css_fighter_selected(ctx: &InlineCtx) {
     GAME_INFO.players[port].skin.store(skin, Ordering::SeqCst);
 }
 
+/// Finds each hook's target in this game version, unless the config turns the hook off or pins its
+/// offset.
 fn search_offsets() {
+    let (hooks, offsets) = (&CONFIG.hooks, &CONFIG.offsets);
     unsafe {
         let text_ptr = getRegionAddress(Region::Text) as *const u8;
         let text_size = (getRegionAddress(Region::Rodata) as usize) - (text_ptr as usize);
         let text = std::slice::from_raw_parts(text_ptr, text_size);
 
-        if let Some(offset) = find_subsequence(text, FIGHTER_SELECTED_SEARCH_CODE) {
-            FIGHTER_SELECTED_OFFSET = offset;
-        } else {
-            println!("Error: no offset found for 'css_fighter_selected'. Defaulting to 13.0.2 offset. This likely won't work.");
+        if hooks.css_fighter_selected {
+            if let Some(offset) = offsets.css_fighter_selected.or_else(|| find_subsequence(text, FIGHTER_SELECTED_SEARCH_CODE)) {
+                FIGHTER_SELECTED_OFFSET = offset;
+                FIGHTER_SELECTED_OFFSET_FOUND.store(true, Ordering::SeqCst);
+            } else {
+                println!("Error: no offset found for 'css_fighter_selected'. Defaulting to 13.0.2 offset. This likely won't work.");
+            }
+        }
+
+        if hooks.arena_id {
+            let offset1 = offsets.arena_id_strlen.or_else(|| find_subsequence(text, OFFSET1_SEARCH_CODE).map(|offset| offset + 0x38));
+            if let Some(offset) = offset1 {
+                OFFSET1 = offset;
+            }
+            let offset2 = offsets.arena_id_caller.or_else(|| find_subsequence(text, OFFSET2_SEARCH_CODE).map(|offset| offset - 0xc));
+            if let Some(offset) = offset2 {
+                OFFSET2 = offset;
+            }
+            ARENA_ID_OFFSETS_FOUND.store(offset1.is_some() && offset2.is_some(), Ordering::SeqCst);
+        }
+
+        if hooks.close_arena {
+            if let Some(offset) = offsets.close_arena.or_else(|| find_subsequence(text, OFFSET3_SEARCH_CODE)) {
+                OFFSET3 = offset;
+                CLOSE_ARENA_OFFSET_FOUND.store(true, Ordering::SeqCst);
+            }
+        }
+
+        if let Some(offset) = offsets.stage_selected {
+            STAGE_SELECTED_OFFSET = offset;
+        }
+        if let Some(offset) = offsets.update_tag_for_player {
+            UPDATE_TAG_FOR_PLAYER_OFFSET = offset;
+        }
+        if let Some(offset) = offsets.player_save {
+            PLAYER_SAVE_OFFSET = offset;
+        }
+        if let Some(offset) = offsets.player_tag {
+            PLAYER_TAG_OFFSET = offset;
         }
     }
 }
 
-#[skyline::hook(offset = 0x2335164, inline)]
+static mut STAGE_SELECTED_OFFSET: usize = 0x2335164;
+
+#[skyline::hook(offset = STAGE_SELECTED_OFFSET, inline)]
 unsafe fn selected_stage(ctx: &InlineCtx) {
     println!("stage has been selected");
     GAME_INFO.is_results_screen.store(false, Ordering::SeqCst);
@@ -596,38 +537,68 @@ pub fn main() {
             &mut FIGHTER_MANAGER_ADDR,
             "_ZN3lib9SingletonIN3app14FighterManagerEE9instance_E\u{0}".as_bytes().as_ptr(),
         );
-        let text_ptr = getRegionAddress(Region::Text) as *const u8;
-        let text_size = (getRegionAddress(Region::Rodata) as usize) - (text_ptr as usize);
-        let text = std::slice::from_raw_parts(text_ptr, text_size);
-        if let Some(offset) = find_subsequence(text, OFFSET1_SEARCH_CODE) {
-            OFFSET1 = offset + 0x38;
-        }
-        if let Some(offset) = find_subsequence(text, OFFSET2_SEARCH_CODE) {
-            OFFSET2 = offset - 0xc;
-        }
-        if let Some(offset) = find_subsequence(text, OFFSET3_SEARCH_CODE) {
-            OFFSET3 = offset;
-        }
     }
-    skyline::install_hooks!(
-        some_strlen_thing,
-        close_arena,
-        update_tag_for_player,
-        css_fighter_selected,
-        selected_stage,
-        special_lw_open_command_hook,
-        special_lw_close_window_hook,
-        special_lw_decide_command_hook,
-        special_lw_select_index_hook
-    );
-    acmd::add_custom_hooks!(once_per_frame_per_fighter);
+
+    let hooks = &CONFIG.hooks;
+    if hooks.arena_id {
+        skyline::install_hook!(some_strlen_thing);
+    }
+    if hooks.close_arena {
+        skyline::install_hook!(close_arena);
+    }
+    if hooks.player_tags {
+        skyline::install_hook!(update_tag_for_player);
+    }
+    if hooks.css_fighter_selected {
+        skyline::install_hook!(css_fighter_selected);
+    }
+    if hooks.stage_selected {
+        skyline::install_hook!(selected_stage);
+    }
+    if hooks.hero_menu {
+        skyline::install_hooks!(
+            special_lw_open_command_hook,
+            special_lw_close_window_hook,
+            special_lw_decide_command_hook,
+            special_lw_select_index_hook
+        );
+    }
+    if hooks.per_frame {
+        acmd::add_custom_hooks!(once_per_frame_per_fighter);
+    }
+
+    if let Some(version) = game_version() {
+        println!("smush_info {} running on game version {}", env!("CARGO_PKG_VERSION"), version);
+    }
 
     std::thread::spawn(||{
+        let mut events = EventDeriver::new();
         loop {
-            std::thread::sleep(std::time::Duration::from_secs(5));
-            if let Err(98) = start_server() {
-                break
-            }
+            update_game_info();
+            server::broadcast_events(&events.update(&GAME_INFO.snapshot()));
+            std::thread::sleep(std::time::Duration::from_millis(CONFIG.server.interval_ms));
         }
     });
+
+    let (ports, transports) = (&CONFIG.server, &CONFIG.transports);
+    if transports.tcp {
+        server::spawn_server(ports.port, server::Framing::Lines);
+    }
+    if transports.websocket {
+        server::spawn_server(ports.websocket_port, server::Framing::WebSocket);
+    }
+    if transports.http {
+        server::spawn_server(ports.http_port, server::Framing::Http);
+    }
+
+    if transports.discovery {
+        std::thread::spawn(||{
+            loop {
+                std::thread::sleep(std::time::Duration::from_secs(5));
+                if let Err(98) = discovery::start_responder() {
+                    break
+                }
+            }
+        });
+    }
 }
\ No newline at end of file
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use lazy_static::lazy_static;
+
+use smush_info_shared::events::Event;
+use smush_info_shared::handshake::{Hello, SCHEMA_VERSION};
+use smush_info_shared::server::Server;
+pub use smush_info_shared::server::Framing;
+
+use crate::transport::LibcTransport;
+use crate::{GAME_INFO, CONFIG};
+
+lazy_static! {
+    static ref SERVER: Arc<Server> = Arc::new(Server::new(&GAME_INFO, CONFIG.server.clone(), hello));
+}
+
+fn hello() -> Hello {
+    Hello {
+        plugin_version: env!("CARGO_PKG_VERSION").to_owned(),
+        schema_version: SCHEMA_VERSION,
+        game_version: crate::game_version(),
+        hooks: crate::resolved_hooks(),
+    }
+}
+
+/// Sends events to every client that isn't paused, as soon as they're derived.
+pub fn broadcast_events(events: &[Event]) {
+    SERVER.broadcast_events(events);
+}
+
+/// Called once per game frame from the game loop.
+pub fn advance_frame() {
+    GAME_INFO.frame.fetch_add(1, Ordering::SeqCst);
+    SERVER.notify_frame();
+}
+
+/// Serves `framing` on `port` from a thread of its own, until the port turns out to be taken.
+pub fn spawn_server(port: u16, framing: Framing) {
+    std::thread::spawn(move || {
+        let err = SERVER.run::<LibcTransport>(port, framing);
+        println!("[smush_info] not serving port {}: {}", port, err);
+    });
+}
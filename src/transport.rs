@@ -0,0 +1,185 @@
+use skyline::libc::*;
+use std::io;
+use std::mem::size_of_val;
+use std::net::SocketAddrV4;
+use std::time::{Duration, Instant};
+
+use smush_info_shared::server::{Transport, Connection};
+
+// what the Switch's socket calls report for the errors the server cares about
+const EINTR: i64 = 4;
+const EAGAIN: i64 = 11;
+const EPIPE: i64 = 32;
+const EADDRINUSE: i64 = 98;
+
+// how long to wait for room in the send buffer before trying again
+const SEND_RETRY_DELAY: Duration = Duration::from_millis(1);
+
+// how long a send may go without writing anything before the client is given up on
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn errno_error(errno: i64) -> io::Error {
+    match errno {
+        EINTR => io::ErrorKind::Interrupted.into(),
+        EAGAIN => io::ErrorKind::WouldBlock.into(),
+        EPIPE => io::ErrorKind::BrokenPipe.into(),
+        EADDRINUSE => io::ErrorKind::AddrInUse.into(),
+        errno => io::Error::from_raw_os_error(errno as i32),
+    }
+}
+
+fn last_error() -> io::Error {
+    errno_error(unsafe { *errno_loc() })
+}
+
+/// A listening TCP socket from the Switch's own socket API.
+pub struct LibcTransport {
+    socket: i32,
+    addr: sockaddr_in,
+}
+
+impl Transport for LibcTransport {
+    type Connection = LibcConnection;
+
+    fn bind(addr: SocketAddrV4, backlog: usize) -> io::Result<Self> {
+        unsafe {
+            let server_addr: sockaddr_in = sockaddr_in {
+                sin_family: AF_INET as _,
+                sin_port: addr.port().to_be(),
+                sin_len: 4,
+                sin_addr: in_addr {
+                    s_addr: u32::from(*addr.ip()).to_be(),
+                },
+                sin_zero: [0; 8],
+            };
+
+            let tcp_socket = socket(AF_INET, SOCK_STREAM, 0);
+            if (tcp_socket as u32 & 0x80000000) != 0 {
+                return Err(last_error());
+            }
+            // closes the socket if any of the setup below fails
+            let transport = LibcTransport { socket: tcp_socket, addr: server_addr };
+
+            let flags: u32 = 1;
+            if setsockopt(
+                tcp_socket,
+                SOL_SOCKET,
+                SO_KEEPALIVE,
+                &flags as *const _ as *const c_void,
+                size_of_val(&flags) as u32,
+            ) < 0 {
+                return Err(last_error());
+            }
+
+            if bind(
+                tcp_socket,
+                &server_addr as *const sockaddr_in as *const sockaddr,
+                size_of_val(&server_addr) as u32,
+            ) < 0 {
+                return Err(last_error());
+            }
+
+            if listen(tcp_socket, backlog as i32) < 0 {
+                return Err(last_error());
+            }
+
+            Ok(transport)
+        }
+    }
+
+    fn accept(&mut self) -> io::Result<LibcConnection> {
+        unsafe {
+            let mut client_addr = self.addr;
+            let mut addr_len = size_of_val(&client_addr) as u32;
+
+            let socket = accept(
+                self.socket,
+                &mut client_addr as *mut sockaddr_in as *mut sockaddr,
+                &mut addr_len,
+            );
+            if socket < 0 {
+                return Err(last_error());
+            }
+
+            // a blocking send to a client that stopped reading reports EAGAIN after this long
+            // instead of hanging; if the option isn't taken, `send` still gives up on EAGAIN
+            let timeout = timeval {
+                tv_sec: SEND_TIMEOUT.as_secs() as _,
+                tv_usec: 0,
+            };
+            setsockopt(
+                socket,
+                SOL_SOCKET,
+                SO_SNDTIMEO,
+                &timeout as *const _ as *const c_void,
+                size_of_val(&timeout) as u32,
+            );
+
+            Ok(LibcConnection { socket })
+        }
+    }
+}
+
+impl Drop for LibcTransport {
+    fn drop(&mut self) {
+        unsafe {
+            close(self.socket);
+        }
+    }
+}
+
+pub struct LibcConnection {
+    socket: i32,
+}
+
+impl Connection for LibcConnection {
+    fn send(&self, mut bytes: &[u8]) -> io::Result<()> {
+        // `send` may take only part of the buffer, so keep going until all of it is written,
+        // unless the client has taken nothing for `SEND_TIMEOUT`
+        let mut last_progress = Instant::now();
+        while !bytes.is_empty() {
+            let ret = unsafe { send(self.socket, bytes.as_ptr() as *const _, bytes.len(), 0) };
+            if ret > 0 {
+                bytes = &bytes[ret as usize..];
+                last_progress = Instant::now();
+                continue;
+            }
+            if ret == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            let err = last_error();
+            match err.kind() {
+                io::ErrorKind::Interrupted => (),
+                io::ErrorKind::WouldBlock if last_progress.elapsed() >= SEND_TIMEOUT => {
+                    return Err(io::ErrorKind::TimedOut.into());
+                }
+                io::ErrorKind::WouldBlock => std::thread::sleep(SEND_RETRY_DELAY),
+                _ => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = unsafe { recv(self.socket, buf.as_mut_ptr() as *mut c_void, buf.len(), 0) };
+        if len < 0 {
+            Err(last_error())
+        } else {
+            Ok(len as usize)
+        }
+    }
+
+    fn shutdown(&self) {
+        unsafe {
+            shutdown(self.socket, SHUT_RDWR);
+        }
+    }
+}
+
+impl Drop for LibcConnection {
+    fn drop(&mut self) {
+        unsafe {
+            close(self.socket);
+        }
+    }
+}